@@ -0,0 +1,157 @@
+use std::fs;
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+
+use serde::{Serialize, Deserialize};
+
+use crate::AppInfo;
+use crate::history::write_atomic;
+
+const INDEX_VERSION: u32 = 1;
+
+#[derive(Serialize, Deserialize)]
+struct IndexFile {
+    version: u32,
+    apps: Vec<AppInfo>,
+}
+
+#[derive(Serialize, Clone, Default, Debug)]
+pub struct AppIndexDiff {
+    pub added: Vec<AppInfo>,
+    pub removed: Vec<String>,
+    pub changed: Vec<AppInfo>,
+}
+
+impl AppIndexDiff {
+    pub fn is_empty(&self) -> bool {
+        self.added.is_empty() && self.removed.is_empty() && self.changed.is_empty()
+    }
+}
+
+pub fn index_path() -> PathBuf {
+    dirs::cache_dir()
+        .unwrap_or_else(|| PathBuf::from("."))
+        .join("bsearch")
+        .join("app_index.json")
+}
+
+pub fn load_index(path: &Path) -> Vec<AppInfo> {
+    let content = match fs::read_to_string(path) {
+        Ok(content) => content,
+        Err(_) => return Vec::new(),
+    };
+
+    match serde_json::from_str::<IndexFile>(&content) {
+        Ok(file) if file.version == INDEX_VERSION => file.apps,
+        Ok(file) => {
+            eprintln!("Ignoring app index with unsupported version {}", file.version);
+            Vec::new()
+        }
+        Err(e) => {
+            eprintln!("Failed to load app index {}: {}", path.display(), e);
+            Vec::new()
+        }
+    }
+}
+
+pub fn save_index(path: &Path, apps: &[AppInfo]) -> std::io::Result<()> {
+    let file = IndexFile {
+        version: INDEX_VERSION,
+        apps: apps.to_vec(),
+    };
+    let data = serde_json::to_vec(&file)?;
+
+    write_atomic(path, &data)
+}
+
+pub fn diff_apps(old: &[AppInfo], new: &[AppInfo]) -> AppIndexDiff {
+    let old_by_path: HashMap<&str, &AppInfo> = old.iter().map(|app| (app.path.as_str(), app)).collect();
+    let new_by_path: HashMap<&str, &AppInfo> = new.iter().map(|app| (app.path.as_str(), app)).collect();
+
+    let mut diff = AppIndexDiff::default();
+
+    for (path, app) in &new_by_path {
+        match old_by_path.get(path) {
+            None => diff.added.push((*app).clone()),
+            Some(old_app) if old_app != app => diff.changed.push((*app).clone()),
+            Some(_) => {}
+        }
+    }
+
+    for path in old_by_path.keys() {
+        if !new_by_path.contains_key(path) {
+            diff.removed.push(path.to_string());
+        }
+    }
+
+    diff
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn app(path: &str, name: &str) -> AppInfo {
+        AppInfo { name: name.to_string(), path: path.to_string(), ..Default::default() }
+    }
+
+    fn index_file(name: &str) -> PathBuf {
+        std::env::temp_dir().join(format!("bsearch-app-index-{}-{}.json", name, std::process::id()))
+    }
+
+    fn paths(apps: &[AppInfo]) -> Vec<&str> {
+        let mut paths: Vec<&str> = apps.iter().map(|app| app.path.as_str()).collect();
+        paths.sort();
+        paths
+    }
+
+    #[test]
+    fn diff_reports_added_removed_and_changed_apps() {
+        let old = [app("/a", "Alpha"), app("/b", "Beta"), app("/c", "Gamma"), app("/d", "Delta")];
+        let new = [app("/a", "Alpha"), app("/b", "Beta 2"), app("/e", "Epsilon"), app("/f", "Zeta")];
+
+        let mut diff = diff_apps(&old, &new);
+        diff.removed.sort();
+
+        assert_eq!(paths(&diff.added), ["/e", "/f"]);
+        assert_eq!(diff.removed, ["/c", "/d"]);
+        assert_eq!(paths(&diff.changed), ["/b"]);
+        assert_eq!(diff.changed[0].name, "Beta 2", "changed apps carry the new info");
+
+        assert!(diff_apps(&new, &new).is_empty());
+    }
+
+    #[test]
+    fn round_trips_saved_apps() {
+        let path = index_file("round-trip");
+        let apps = vec![
+            AppInfo { icon_path: Some("/cache/a.png".to_string()), keywords: vec!["x".to_string()], ..app("/a", "Alpha") },
+            app("/b", "Beta"),
+        ];
+
+        save_index(&path, &apps).unwrap();
+        let loaded = load_index(&path);
+        fs::remove_file(&path).unwrap();
+
+        assert_eq!(loaded, apps);
+    }
+
+    #[test]
+    fn ignores_unknown_versions_and_corrupt_files() {
+        let path = index_file("unreadable");
+        let future = serde_json::to_string(&IndexFile { version: INDEX_VERSION + 1, apps: vec![app("/a", "Alpha")] }).unwrap();
+        let cases = [
+            future.as_str(),
+            r#"{"version":1,"apps":[{"name":"Alpha""#,
+            "not json",
+        ];
+
+        for content in cases {
+            fs::write(&path, content).unwrap();
+            assert!(load_index(&path).is_empty(), "index {:?}", content);
+        }
+        fs::remove_file(&path).unwrap();
+
+        assert!(load_index(&path).is_empty(), "missing index");
+    }
+}
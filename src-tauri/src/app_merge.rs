@@ -0,0 +1,115 @@
+use std::env;
+use std::fs;
+use std::collections::HashMap;
+use std::path::{Component, Path, PathBuf};
+
+use crate::AppInfo;
+use crate::shell_link::expand_vars;
+
+// The same program is often found several times: a Start Menu shortcut, a
+// desktop shortcut, App Paths and an Uninstall entry. This folds those into
+// one entry per launch target.
+
+fn normalize_lexically(path: &Path) -> PathBuf {
+    let mut normalized = PathBuf::new();
+    for component in path.components() {
+        match component {
+            Component::CurDir => {}
+            Component::ParentDir => {
+                normalized.pop();
+            }
+            other => normalized.push(other),
+        }
+    }
+    normalized
+}
+
+// Expands environment variables and resolves symlinks and `..`, falling back
+// to lexical cleanup for targets that don't exist. Windows paths are case
+// folded since the file system ignores case there.
+pub fn canonical_path(path: &str) -> String {
+    let expanded = expand_vars(path.trim().trim_matches('"'), |name| env::var(name).ok());
+    let path = Path::new(&expanded);
+    let resolved = fs::canonicalize(path).unwrap_or_else(|_| normalize_lexically(path));
+
+    let resolved = resolved.to_string_lossy();
+    let resolved = resolved.strip_prefix(r"\\?\").unwrap_or(&resolved);
+    if cfg!(windows) {
+        resolved.to_lowercase()
+    } else {
+        resolved.to_string()
+    }
+}
+
+// Shortcuts and desktop entries carry names chosen for people; Uninstall
+// entries have a DisplayName; App Paths only have a file name.
+fn name_rank(app: &AppInfo) -> u8 {
+    if app.is_shortcut || app.exec.is_some() {
+        0
+    } else if app.selection_rationale.is_some() {
+        1
+    } else {
+        2
+    }
+}
+
+// An explicitly chosen icon beats one extracted from the target itself.
+fn icon_rank(app: &AppInfo) -> u8 {
+    match (&app.icon_name, &app.icon_path) {
+        (Some(_), Some(_)) => 0,
+        (None, Some(_)) => 1,
+        _ => 2,
+    }
+}
+
+fn merge_group(group: Vec<&AppInfo>) -> AppInfo {
+    let best = group.iter().min_by_key(|app| name_rank(app)).unwrap();
+    let icon = group.iter().min_by_key(|app| (icon_rank(app), name_rank(app))).unwrap();
+
+    let mut merged = (*best).clone();
+    merged.icon_path = icon.icon_path.clone();
+    merged.icon_path_2x = icon.icon_path_2x.clone();
+    merged.icon_name = icon.icon_name.clone();
+    merged.icon_index = icon.icon_index;
+
+    merged.origins = Vec::new();
+    for app in &group {
+        let origins = if app.origins.is_empty() {
+            vec![app.source.clone().unwrap_or_else(|| app.path.clone())]
+        } else {
+            app.origins.clone()
+        };
+        for origin in origins {
+            if !merged.origins.contains(&origin) {
+                merged.origins.push(origin);
+            }
+        }
+    }
+
+    merged
+}
+
+// Groups apps that launch the same target with the same arguments (and, for
+// Wine, in the same prefix), keeping the order in which they were found.
+pub fn merge_apps(apps: &[AppInfo]) -> Vec<AppInfo> {
+    let mut groups: Vec<Vec<&AppInfo>> = Vec::new();
+    let mut index: HashMap<(String, &str, &str), usize> = HashMap::new();
+
+    for app in apps {
+        let key = (
+            canonical_path(&app.path),
+            app.arguments.as_deref().unwrap_or_default(),
+            app.wine_prefix.as_deref().unwrap_or_default(),
+        );
+
+        match index.get(&key) {
+            Some(&i) => groups[i].push(app),
+            None => {
+                index.insert(key, groups.len());
+                groups.push(vec![app]);
+            }
+        }
+    }
+
+    groups.into_iter().map(merge_group).collect()
+}
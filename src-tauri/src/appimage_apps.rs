@@ -0,0 +1,193 @@
+use std::cell::OnceCell;
+use std::fs::{self, File};
+use std::io::Read;
+use std::path::{Path, PathBuf};
+
+use crate::{config, icon_cache, scan, AppInfo, Packaging};
+use crate::desktop_entry::{self, DesktopEntry};
+use crate::squashfs::{self, Archive};
+
+// AppImages carry their desktop entry and icon inside the SquashFS image
+// appended to the runtime. Those are read directly rather than by running
+// the image with --appimage-extract.
+
+const ICON_SIZE: u32 = 48;
+const ELF_MAGIC: &[u8] = b"\x7fELF";
+
+fn appimage_dirs() -> Vec<PathBuf> {
+    config::get().appimage_dirs.iter().map(|dir| config::expand_home(dir)).collect()
+}
+
+pub(crate) struct RescanContext {
+    dirs: Vec<PathBuf>,
+    locales: Vec<String>,
+}
+
+impl RescanContext {
+    fn new() -> Self {
+        Self {
+            dirs: appimage_dirs(),
+            locales: desktop_entry::current_locales(),
+        }
+    }
+}
+
+// Both AppImage types mark the ELF identification padding with `AI` and
+// the type number.
+fn header_type(header: &[u8; 64]) -> Option<u8> {
+    (header.starts_with(ELF_MAGIC) && &header[8..10] == b"AI").then_some(header[10])
+}
+
+fn appimage_type(path: &Path) -> Option<u8> {
+    let mut header = [0u8; 64];
+    File::open(path).and_then(|mut file| file.read_exact(&mut header)).ok()?;
+
+    header_type(&header)
+}
+
+fn is_appimage(path: &Path) -> bool {
+    path.is_file() && appimage_type(path).is_some()
+}
+
+// Type 2 images start right after the runtime, which ends with its section
+// header table.
+fn open_archive(path: &Path) -> Result<Archive, String> {
+    let mut file = File::open(path).map_err(|e| e.to_string())?;
+    let mut header = [0u8; 64];
+    file.read_exact(&mut header).map_err(|e| e.to_string())?;
+
+    if header_type(&header) != Some(2) {
+        return Err("only type 2 AppImages are supported".to_string());
+    }
+
+    let u16_at = |offset: usize| u16::from_le_bytes([header[offset], header[offset + 1]]) as u64;
+    let u32_at = |offset: usize| u32::from_le_bytes(header[offset..offset + 4].try_into().unwrap()) as u64;
+    let u64_at = |offset: usize| u64::from_le_bytes(header[offset..offset + 8].try_into().unwrap());
+
+    let offset = match header[4] {
+        1 => u32_at(0x20) + u16_at(0x2E) * u16_at(0x30),
+        2 => u64_at(0x28) + u16_at(0x3A) * u16_at(0x3C),
+        _ => return Err("unknown ELF class".to_string()),
+    };
+
+    Archive::open(file, offset)
+}
+
+fn read_desktop_entry(archive: &mut Archive, locales: &[String]) -> Result<DesktopEntry, String> {
+    let name = archive.root_entries()?
+        .into_iter()
+        .find(|name| name.ends_with(".desktop"))
+        .ok_or("no desktop entry")?;
+    let content = archive.read_file(&name)?;
+
+    DesktopEntry::parse(&String::from_utf8_lossy(&content), locales)
+        .ok_or_else(|| format!("invalid desktop entry {}", name))
+}
+
+// `.DirIcon` is what the spec asks for; older images only have the icon
+// named after the entry's Icon key, at the root or in hicolor.
+fn read_icon(archive: &mut Archive, icon: Option<&str>) -> Option<Vec<u8>> {
+    let mut candidates = vec![".DirIcon".to_string()];
+    if let Some(icon) = icon {
+        for ext in ["png", "svg", "svgz"] {
+            candidates.push(format!("{}.{}", icon, ext));
+        }
+        candidates.push(format!("usr/share/icons/hicolor/256x256/apps/{}.png", icon));
+        candidates.push(format!("usr/share/icons/hicolor/scalable/apps/{}.svg", icon));
+    }
+
+    candidates.iter().find_map(|candidate| archive.read_file(candidate).ok())
+}
+
+fn render_icon(path: &Path, data: Option<&[u8]>, size: u32) -> Option<String> {
+    let data = data?;
+    icon_cache::global().get_or_insert_with(path, &format!("appimage-{}", size), |out| {
+        icon_cache::render_icon_data(data, &path.display().to_string(), size, out)
+    })
+}
+
+fn appimage_to_app(path: &Path, locales: &[String]) -> Option<AppInfo> {
+    let stem = path.file_stem()?.to_string_lossy().to_string();
+
+    let mut archive = open_archive(path);
+    let entry = match archive.as_mut().map_err(|e| e.clone()).and_then(|archive| read_desktop_entry(archive, locales)) {
+        Ok(entry) => Some(entry),
+        // A known limitation rather than a broken file; the image is still
+        // listed under its file name.
+        Err(e) if e.starts_with(squashfs::UNSUPPORTED_COMPRESSION) => None,
+        Err(e) => {
+            eprintln!("Failed to read AppImage metadata {}: {}", path.display(), e);
+            None
+        }
+    };
+    let icon_name = entry.as_ref().and_then(|entry| entry.string("Icon"));
+    let icon_data = archive.ok().and_then(|mut archive| read_icon(&mut archive, icon_name.as_deref()));
+
+    Some(AppInfo {
+        name: entry.as_ref().and_then(|entry| entry.localized("Name")).unwrap_or(stem),
+        path: path.to_string_lossy().to_string(),
+        icon_path: render_icon(path, icon_data.as_deref(), ICON_SIZE)
+            .or_else(|| icon_cache::global().default_icon()),
+        icon_path_2x: render_icon(path, icon_data.as_deref(), ICON_SIZE * 2),
+        is_shortcut: true,
+        generic_name: entry.as_ref().and_then(|entry| entry.localized("GenericName")),
+        keywords: entry.as_ref().map(|entry| entry.localized_list("Keywords")).unwrap_or_default(),
+        icon_name,
+        source: Some(path.to_string_lossy().to_string()),
+        packaging: Some(Packaging::AppImage),
+        ..Default::default()
+    })
+}
+
+pub(crate) fn collect_apps() -> Vec<AppInfo> {
+    use rayon::prelude::*;
+
+    let locales = desktop_entry::current_locales();
+
+    let mut paths: Vec<PathBuf> = appimage_dirs()
+        .iter()
+        .filter_map(|dir| fs::read_dir(dir).ok())
+        .flat_map(|entries| entries.filter_map(|e| e.ok()).map(|e| e.path()))
+        .filter(|path| !scan::is_excluded(path) && is_appimage(path))
+        .collect();
+    paths.sort();
+
+    paths.par_iter()
+        .filter_map(|path| appimage_to_app(path, &locales))
+        .collect()
+}
+
+// Takes the AppImages out of `files`, which come from the extra scan roots.
+pub(crate) fn root_apps(files: &mut Vec<PathBuf>) -> Vec<AppInfo> {
+    use rayon::prelude::*;
+
+    let (paths, rest): (Vec<_>, Vec<_>) = files.drain(..).partition(|path| is_appimage(path));
+    *files = rest;
+
+    let locales = desktop_entry::current_locales();
+    paths.par_iter()
+        .filter_map(|path| appimage_to_app(path, &locales))
+        .collect()
+}
+
+pub(crate) fn watch_roots() -> Vec<PathBuf> {
+    appimage_dirs()
+}
+
+// `context` is shared by the files of a watcher batch.
+pub(crate) fn rescan_file(path: &Path, context: &OnceCell<RescanContext>) -> Option<(Vec<String>, Vec<AppInfo>)> {
+    let context = context.get_or_init(RescanContext::new);
+
+    let parent = path.parent()?;
+    if !context.dirs.iter().any(|dir| dir == parent) {
+        return None;
+    }
+
+    let apps = if is_appimage(path) {
+        appimage_to_app(path, &context.locales).into_iter().collect()
+    } else {
+        Vec::new()
+    };
+
+    Some((vec![path.to_string_lossy().to_string()], apps))
+}
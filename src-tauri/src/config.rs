@@ -0,0 +1,107 @@
+use std::fs;
+use std::path::PathBuf;
+use std::sync::OnceLock;
+
+use serde::{Serialize, Deserialize};
+
+static CONFIG: OnceLock<Config> = OnceLock::new();
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+#[serde(default)]
+pub struct Config {
+    /// Command line used to wrap `Terminal=true` entries, e.g. `"kitty -e"`.
+    /// When unset, `$TERMINAL` and a list of common emulators are tried.
+    pub terminal: Option<String>,
+    /// Upper bound for the rendered icon cache, in megabytes.
+    pub icon_cache_max_mb: u64,
+    /// Freedesktop icon theme to resolve `Icon=` names against. When unset,
+    /// the GTK and KDE settings files are consulted.
+    pub icon_theme: Option<String>,
+    /// Wine prefixes whose Windows applications are indexed. When empty,
+    /// `$WINEPREFIX`, `~/.wine` and winetricks' prefixes are used.
+    pub wine_prefixes: Vec<String>,
+    /// Directories searched (non-recursively) for AppImages. Names and icons
+    /// are read from images compressed with gzip, xz or zstd; others (lzo,
+    /// lz4) are listed under their file name with the default icon.
+    pub appimage_dirs: Vec<String>,
+    /// Extra scan roots and rules applied to every directory walk.
+    pub scan: ScanConfig,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, Default)]
+#[serde(default)]
+pub struct ScanConfig {
+    /// Directories indexed in addition to each platform's standard locations.
+    pub roots: Vec<ScanRoot>,
+    /// Glob patterns for files and directories to skip during discovery.
+    /// Patterns without a `/` match file names; `**` spans directories.
+    pub exclude: Vec<String>,
+    /// Whether directory walks follow symbolic links. Off by default; XDG
+    /// application directories are always followed.
+    pub follow_symlinks: bool,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct ScanRoot {
+    pub path: String,
+    /// How deep to descend, where 1 is the root's own files. Unlimited when
+    /// unset.
+    #[serde(default)]
+    pub max_depth: Option<usize>,
+    /// Extensions to index (`.lnk`, `.desktop`, `.sh`, ...), plus
+    /// `executable` for any file that can be run directly. Defaults to
+    /// `.desktop`, `.lnk` and `executable`.
+    #[serde(default)]
+    pub extensions: Vec<String>,
+    /// Overrides `follow_symlinks` for this root.
+    #[serde(default)]
+    pub follow_symlinks: Option<bool>,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            terminal: None,
+            icon_cache_max_mb: 64,
+            icon_theme: None,
+            wine_prefixes: Vec::new(),
+            appimage_dirs: vec!["~/Applications".to_string(), "~/bin".to_string()],
+            scan: ScanConfig::default(),
+        }
+    }
+}
+
+pub fn config_dir() -> PathBuf {
+    dirs::config_dir()
+        .unwrap_or_else(|| PathBuf::from("."))
+        .join("bsearch")
+}
+
+pub fn data_dir() -> PathBuf {
+    dirs::data_dir()
+        .unwrap_or_else(|| PathBuf::from("."))
+        .join("bsearch")
+}
+
+pub fn expand_home(path: &str) -> PathBuf {
+    match (path.strip_prefix("~/"), dirs::home_dir()) {
+        (Some(rest), Some(home)) => home.join(rest),
+        _ => PathBuf::from(path),
+    }
+}
+
+fn load_config() -> Config {
+    let path = config_dir().join("config.json");
+
+    match fs::read_to_string(&path) {
+        Ok(content) => serde_json::from_str(&content).unwrap_or_else(|e| {
+            eprintln!("Failed to parse {}: {}", path.display(), e);
+            Config::default()
+        }),
+        Err(_) => Config::default(),
+    }
+}
+
+pub fn get() -> &'static Config {
+    CONFIG.get_or_init(load_config)
+}
@@ -0,0 +1,223 @@
+use std::env;
+use std::path::Path;
+use std::collections::HashMap;
+use std::os::unix::fs::PermissionsExt;
+
+pub struct DesktopEntry {
+    fields: HashMap<String, String>,
+    locales: Vec<String>,
+}
+
+// Parses the INI-like format shared by desktop entries and icon theme index
+// files into `group -> key -> raw value`. The first occurrence of a key wins.
+pub fn parse_groups(content: &str) -> HashMap<String, HashMap<String, String>> {
+    let mut groups: HashMap<String, HashMap<String, String>> = HashMap::new();
+    let mut current_group: Option<String> = None;
+
+    for line in content.lines() {
+        let line = line.trim();
+
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+
+        if line.starts_with('[') && line.ends_with(']') {
+            let name = line[1..line.len() - 1].to_string();
+            groups.entry(name.clone()).or_default();
+            current_group = Some(name);
+            continue;
+        }
+
+        let group = match &current_group {
+            Some(group) => group,
+            None => continue,
+        };
+
+        if let Some((key, value)) = line.split_once('=') {
+            groups.entry(group.clone())
+                .or_default()
+                .entry(key.trim().to_string())
+                .or_insert_with(|| value.trim().to_string());
+        }
+    }
+
+    groups
+}
+
+impl DesktopEntry {
+    pub fn parse(content: &str, locales: &[String]) -> Option<Self> {
+        let fields = parse_groups(content).remove("Desktop Entry")?;
+
+        Some(Self {
+            fields,
+            locales: locales.to_vec(),
+        })
+    }
+
+    pub fn raw(&self, key: &str) -> Option<&str> {
+        self.fields.get(key).map(|v| v.as_str())
+    }
+
+    pub fn string(&self, key: &str) -> Option<String> {
+        self.raw(key).map(unescape)
+    }
+
+    pub fn localized(&self, key: &str) -> Option<String> {
+        self.localized_raw(key).map(unescape)
+    }
+
+    pub fn boolean(&self, key: &str) -> bool {
+        self.raw(key) == Some("true")
+    }
+
+    pub fn list(&self, key: &str) -> Vec<String> {
+        self.raw(key).map(split_list).unwrap_or_default()
+    }
+
+    pub fn localized_list(&self, key: &str) -> Vec<String> {
+        self.localized_raw(key).map(split_list).unwrap_or_default()
+    }
+
+    fn localized_raw(&self, key: &str) -> Option<&str> {
+        self.locales.iter()
+            .find_map(|locale| self.raw(&format!("{}[{}]", key, locale)))
+            .or_else(|| self.raw(key))
+    }
+
+    pub fn is_application(&self) -> bool {
+        self.raw("Type") == Some("Application")
+    }
+
+    pub fn should_show(&self, current_desktops: &[String]) -> bool {
+        if !self.is_application() || self.boolean("NoDisplay") || self.boolean("Hidden") {
+            return false;
+        }
+
+        let only_show_in = self.list("OnlyShowIn");
+        if !only_show_in.is_empty() && !only_show_in.iter().any(|d| current_desktops.contains(d)) {
+            return false;
+        }
+
+        if self.list("NotShowIn").iter().any(|d| current_desktops.contains(d)) {
+            return false;
+        }
+
+        match self.string("TryExec") {
+            Some(try_exec) => executable_exists(&try_exec),
+            None => true,
+        }
+    }
+}
+
+fn unescape(value: &str) -> String {
+    let mut result = String::with_capacity(value.len());
+    let mut chars = value.chars();
+
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            result.push(c);
+            continue;
+        }
+
+        match chars.next() {
+            Some('s') => result.push(' '),
+            Some('n') => result.push('\n'),
+            Some('t') => result.push('\t'),
+            Some('r') => result.push('\r'),
+            Some('\\') => result.push('\\'),
+            Some(other) => {
+                result.push('\\');
+                result.push(other);
+            }
+            None => result.push('\\'),
+        }
+    }
+
+    result
+}
+
+fn split_list(value: &str) -> Vec<String> {
+    let mut items = Vec::new();
+    let mut current = String::new();
+    let mut chars = value.chars();
+
+    while let Some(c) = chars.next() {
+        match c {
+            '\\' => match chars.next() {
+                Some(';') => current.push(';'),
+                Some(other) => {
+                    current.push('\\');
+                    current.push(other);
+                }
+                None => current.push('\\'),
+            },
+            ';' => items.push(std::mem::take(&mut current)),
+            _ => current.push(c),
+        }
+    }
+    items.push(current);
+
+    items.iter()
+        .map(|item| unescape(item.trim()))
+        .filter(|item| !item.is_empty())
+        .collect()
+}
+
+pub fn executable_exists(program: &str) -> bool {
+    let is_executable = |path: &Path| {
+        path.metadata()
+            .map(|m| m.is_file() && m.permissions().mode() & 0o111 != 0)
+            .unwrap_or(false)
+    };
+
+    if program.contains('/') {
+        return is_executable(Path::new(program));
+    }
+
+    env::var_os("PATH")
+        .map(|paths| env::split_paths(&paths).any(|dir| is_executable(&dir.join(program))))
+        .unwrap_or(false)
+}
+
+// Expands e.g. `zh_CN.UTF-8@mod` into the lookup order the spec prescribes:
+// lang_COUNTRY@MODIFIER, lang_COUNTRY, lang@MODIFIER, lang.
+pub fn current_locales() -> Vec<String> {
+    let locale = ["LC_ALL", "LC_MESSAGES", "LANG"].iter()
+        .filter_map(|var| env::var(var).ok())
+        .find(|value| !value.is_empty())
+        .unwrap_or_default();
+
+    let (locale, modifier) = match locale.split_once('@') {
+        Some((locale, modifier)) => (locale.to_string(), Some(modifier.to_string())),
+        None => (locale, None),
+    };
+    let locale = locale.split('.').next().unwrap_or("").to_string();
+    let (lang, country) = match locale.split_once('_') {
+        Some((lang, country)) => (lang.to_string(), Some(country.to_string())),
+        None => (locale, None),
+    };
+
+    if lang.is_empty() || lang == "C" || lang == "POSIX" {
+        return Vec::new();
+    }
+
+    let mut locales = Vec::new();
+    if let (Some(country), Some(modifier)) = (&country, &modifier) {
+        locales.push(format!("{}_{}@{}", lang, country, modifier));
+    }
+    if let Some(country) = &country {
+        locales.push(format!("{}_{}", lang, country));
+    }
+    if let Some(modifier) = &modifier {
+        locales.push(format!("{}@{}", lang, modifier));
+    }
+    locales.push(lang);
+
+    locales
+}
+
+pub fn current_desktops() -> Vec<String> {
+    env::var("XDG_CURRENT_DESKTOP")
+        .map(|value| value.split(':').filter(|d| !d.is_empty()).map(|d| d.to_string()).collect())
+        .unwrap_or_default()
+}
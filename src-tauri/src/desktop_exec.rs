@@ -0,0 +1,279 @@
+use std::env;
+use std::process::Command;
+
+use crate::{config, AppInfo};
+use crate::desktop_entry::executable_exists;
+
+const KNOWN_TERMINALS: &[(&str, &[&str])] = &[
+    ("x-terminal-emulator", &["-e"]),
+    ("gnome-terminal", &["--"]),
+    ("konsole", &["-e"]),
+    ("xfce4-terminal", &["-x"]),
+    ("alacritty", &["-e"]),
+    ("kitty", &[]),
+    ("foot", &[]),
+    ("xterm", &["-e"]),
+];
+
+pub struct ExecContext<'a> {
+    pub name: &'a str,
+    pub icon: Option<&'a str>,
+    pub desktop_file: &'a str,
+    pub files: &'a [String],
+}
+
+// Splits an Exec value (already string-unescaped) into arguments following
+// the Desktop Entry quoting rules: double quotes group, and inside them a
+// backslash escapes `"`, `` ` ``, `$` and `\`.
+pub fn tokenize_exec(exec: &str) -> Result<Vec<String>, String> {
+    let mut args = Vec::new();
+    let mut current = String::new();
+    let mut in_arg = false;
+    let mut in_quotes = false;
+    let mut chars = exec.chars();
+
+    while let Some(c) = chars.next() {
+        if in_quotes {
+            match c {
+                '"' => in_quotes = false,
+                '\\' => match chars.next() {
+                    Some(escaped @ ('"' | '`' | '$' | '\\')) => current.push(escaped),
+                    Some(other) => {
+                        current.push('\\');
+                        current.push(other);
+                    }
+                    None => return Err("Unterminated escape in Exec".to_string()),
+                },
+                _ => current.push(c),
+            }
+            continue;
+        }
+
+        match c {
+            '"' => {
+                in_quotes = true;
+                in_arg = true;
+            }
+            ' ' | '\t' | '\n' => {
+                if in_arg {
+                    args.push(std::mem::take(&mut current));
+                    in_arg = false;
+                }
+            }
+            _ => {
+                current.push(c);
+                in_arg = true;
+            }
+        }
+    }
+
+    if in_quotes {
+        return Err("Unterminated quote in Exec".to_string());
+    }
+
+    if in_arg {
+        args.push(current);
+    }
+
+    Ok(args)
+}
+
+pub fn expand_exec(exec: &str, context: &ExecContext) -> Result<Vec<String>, String> {
+    let expanded = expand_args(tokenize_exec(exec)?, context);
+
+    if expanded.is_empty() {
+        return Err("Exec is empty".to_string());
+    }
+
+    Ok(expanded)
+}
+
+// Expands the field codes of already tokenized Exec arguments.
+pub(crate) fn expand_args(args: impl IntoIterator<Item = String>, context: &ExecContext) -> Vec<String> {
+    let mut expanded = Vec::new();
+
+    for arg in args {
+        match arg.as_str() {
+            "%F" | "%U" => expanded.extend(context.files.iter().cloned()),
+            "%i" => {
+                if let Some(icon) = context.icon {
+                    expanded.push("--icon".to_string());
+                    expanded.push(icon.to_string());
+                }
+            }
+            _ => {
+                let (value, only_field_codes) = expand_field_codes(&arg, context);
+
+                // `%f` with nothing to open removes the argument entirely
+                // rather than passing an empty string.
+                if !(value.is_empty() && only_field_codes) {
+                    expanded.push(value);
+                }
+            }
+        }
+    }
+
+    expanded
+}
+
+fn expand_field_codes(arg: &str, context: &ExecContext) -> (String, bool) {
+    let mut result = String::new();
+    let mut only_field_codes = true;
+    let mut chars = arg.chars();
+
+    while let Some(c) = chars.next() {
+        if c != '%' {
+            result.push(c);
+            only_field_codes = false;
+            continue;
+        }
+
+        match chars.next() {
+            Some('%') => {
+                result.push('%');
+                only_field_codes = false;
+            }
+            Some('f' | 'u' | 'F' | 'U') => {
+                if let Some(file) = context.files.first() {
+                    result.push_str(file);
+                }
+            }
+            Some('c') => result.push_str(context.name),
+            Some('k') => result.push_str(context.desktop_file),
+            // %i is only meaningful as a standalone argument; the deprecated
+            // %d/%D/%n/%N/%v/%m codes and unknown ones are dropped.
+            _ => {}
+        }
+    }
+
+    (result, only_field_codes)
+}
+
+pub(crate) fn terminal_command() -> Result<Vec<String>, String> {
+    if let Some(terminal) = &config::get().terminal {
+        return tokenize_exec(terminal);
+    }
+
+    if let Ok(terminal) = env::var("TERMINAL") {
+        if !terminal.is_empty() && executable_exists(&terminal) {
+            return Ok(vec![terminal, "-e".to_string()]);
+        }
+    }
+
+    KNOWN_TERMINALS.iter()
+        .find(|(program, _)| executable_exists(program))
+        .map(|(program, args)| {
+            std::iter::once(program.to_string())
+                .chain(args.iter().map(|a| a.to_string()))
+                .collect()
+        })
+        .ok_or_else(|| "No terminal emulator found for Terminal=true entry".to_string())
+}
+
+pub(crate) fn launch_desktop_app(app: &AppInfo, files: &[String]) -> Result<(), String> {
+    let exec = app.exec.as_deref().ok_or_else(|| format!("{} has no Exec line", app.path))?;
+
+    let context = ExecContext {
+        name: &app.name,
+        icon: app.icon_name.as_deref(),
+        desktop_file: &app.path,
+        files,
+    };
+
+    let mut args = expand_exec(exec, &context)?;
+
+    if app.terminal {
+        let mut terminal = terminal_command()?;
+        terminal.append(&mut args);
+        args = terminal;
+    }
+
+    let mut command = Command::new(&args[0]);
+    command.args(&args[1..]);
+
+    if let Some(working_dir) = &app.working_dir {
+        command.current_dir(working_dir);
+    }
+
+    match command.spawn() {
+        Ok(_) => Ok(()),
+        Err(e) => Err(format!("Open App Failed: {}", e))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn context<'a>(icon: Option<&'a str>, files: &'a [String]) -> ExecContext<'a> {
+        ExecContext {
+            name: "Text Editor",
+            icon,
+            desktop_file: "/usr/share/applications/editor.desktop",
+            files,
+        }
+    }
+
+    #[test]
+    fn tokenizes_quoted_arguments() {
+        let cases: &[(&str, &[&str])] = &[
+            ("editor  --new-window\t%U", &["editor", "--new-window", "%U"]),
+            (r#""/opt/My Editor/editor" --title "a b""#, &["/opt/My Editor/editor", "--title", "a b"]),
+            (r#"run --flag="x y"z"#, &["run", "--flag=x yz"]),
+            (r#"sh -c "echo \"hi\" \$HOME \`date\` \\ \n""#, &["sh", "-c", r#"echo "hi" $HOME `date` \ \n"#]),
+            (r#"editor """#, &["editor", ""]),
+            // Reserved characters outside quotes are passed on literally,
+            // never to a shell.
+            ("editor $HOME >out|tee;x &", &["editor", "$HOME", ">out|tee;x", "&"]),
+        ];
+
+        for (exec, expected) in cases {
+            assert_eq!(tokenize_exec(exec).unwrap(), *expected, "exec {:?}", exec);
+        }
+    }
+
+    #[test]
+    fn rejects_unterminated_quotes() {
+        assert!(tokenize_exec(r#"editor "file"#).is_err());
+        assert!(tokenize_exec(r#"editor "file\"#).is_err());
+    }
+
+    #[test]
+    fn keeps_percent_codes_for_expansion() {
+        assert_eq!(tokenize_exec("printf %%d %f").unwrap(), ["printf", "%%d", "%f"]);
+    }
+
+    #[test]
+    fn expands_field_codes() {
+        let files = ["/tmp/a b.txt".to_string(), "/tmp/c.txt".to_string()];
+        let cases: &[(&str, &[String], &[&str])] = &[
+            // File codes vanish when nothing is opened.
+            ("editor %f %F %u %U", &[], &["editor"]),
+            ("editor --open=%f", &[], &["editor", "--open="]),
+            ("editor %f", &files, &["editor", "/tmp/a b.txt"]),
+            ("editor %F", &files, &["editor", "/tmp/a b.txt", "/tmp/c.txt"]),
+            ("editor %U", &files, &["editor", "/tmp/a b.txt", "/tmp/c.txt"]),
+            ("editor %i", &[], &["editor", "--icon", "accessories-text-editor"]),
+            ("editor --class=%c", &[], &["editor", "--class=Text Editor"]),
+            ("editor %k", &[], &["editor", "/usr/share/applications/editor.desktop"]),
+            ("printf 100%%", &[], &["printf", "100%"]),
+            // Deprecated and unknown codes are dropped.
+            ("editor %d %m --x%z", &[], &["editor", "--x"]),
+        ];
+
+        for (exec, files, expected) in cases {
+            let expanded = expand_exec(exec, &context(Some("accessories-text-editor"), files)).unwrap();
+            assert_eq!(expanded, *expected, "exec {:?} with files {:?}", exec, files);
+        }
+    }
+
+    #[test]
+    fn drops_icon_code_without_icon() {
+        assert_eq!(expand_exec("editor %i", &context(None, &[])).unwrap(), ["editor"]);
+    }
+
+    #[test]
+    fn rejects_exec_with_only_field_codes() {
+        assert!(expand_exec("%f %U", &context(None, &[])).is_err());
+    }
+}
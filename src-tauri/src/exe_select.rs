@@ -0,0 +1,237 @@
+use std::path::{Path, PathBuf};
+
+use crate::scan;
+
+// Picks the program an Uninstall entry refers to out of the executables in
+// its InstallLocation, which usually also holds uninstallers, updaters and
+// crash reporters.
+
+const MAX_DEPTH: usize = 2;
+
+// Matched against lowercased, alphanumeric-only file stems.
+const HELPER_PATTERNS: &[&str] = &[
+    "unins", "uninst", "update", "crashpad", "crashreport", "crashhandler", "bugreport",
+    "errorreport", "setup", "install", "helper", "elevat", "redist", "notification",
+    "squirrel", "launcherprereq", "dxwebsetup",
+];
+
+pub struct Candidate {
+    pub path: PathBuf,
+    pub size: u64,
+    // 1 for files directly inside the install directory.
+    pub depth: usize,
+}
+
+pub struct Selection {
+    pub path: PathBuf,
+    pub score: i32,
+    pub rationale: String,
+}
+
+fn normalize(s: &str) -> String {
+    s.chars()
+        .filter(|c| c.is_alphanumeric())
+        .flat_map(char::to_lowercase)
+        .collect()
+}
+
+fn is_helper(stem: &str) -> bool {
+    HELPER_PATTERNS.iter().any(|pattern| stem.contains(pattern))
+}
+
+pub fn candidates(dir: &Path) -> Vec<Candidate> {
+    scan::walk(dir, Some(MAX_DEPTH))
+        .filter(|e| {
+            e.file_type().is_file()
+                && e.path().extension().is_some_and(|ext| ext.eq_ignore_ascii_case("exe"))
+        })
+        .map(|e| Candidate {
+            size: e.metadata().map(|m| m.len()).unwrap_or(0),
+            depth: e.depth(),
+            path: e.into_path(),
+        })
+        .collect()
+}
+
+// Scores one candidate, returning `None` for helper binaries. The rationale
+// lists every term that contributed to the score.
+fn score(candidate: &Candidate, display_name: &str, display_icon: Option<&Path>) -> Option<(i32, Vec<String>)> {
+    let stem = normalize(&candidate.path.file_stem()?.to_string_lossy());
+    let name = normalize(display_name);
+    let mut score = 0;
+    let mut reasons = Vec::new();
+
+    let mut add = |points: i32, reason: String| {
+        score += points;
+        reasons.push(format!("{} {:+}", reason, points));
+    };
+
+    let name_matches = !stem.is_empty() && stem == name;
+    let name_contains = stem.len() >= 3 && name.contains(&stem);
+    if is_helper(&stem) && !name_contains {
+        return None;
+    }
+
+    let is_icon = display_icon.is_some_and(|icon| {
+        icon.to_string_lossy().eq_ignore_ascii_case(&candidate.path.to_string_lossy())
+    });
+    if is_icon {
+        add(100, "DisplayIcon".to_string());
+    }
+
+    if name_matches {
+        add(50, "name matches".to_string());
+    } else if name_contains {
+        add(30, "name contains stem".to_string());
+    } else {
+        let shared = display_name.split(|c: char| !c.is_alphanumeric())
+            .map(normalize)
+            .filter(|word| word.len() >= 3 && stem.contains(word.as_str()))
+            .count();
+        if shared > 0 {
+            add(15 * shared as i32, format!("{} shared word(s)", shared));
+        }
+    }
+
+    if candidate.depth > 1 {
+        add(-10 * (candidate.depth as i32 - 1), format!("depth {}", candidate.depth));
+    }
+
+    // +1 per quadrupling: the main binary tends to be the largest, but this
+    // should only break ties between otherwise similar candidates.
+    let size_bits = 64 - candidate.size.leading_zeros() as i32;
+    if size_bits > 0 {
+        add(size_bits / 2, format!("size {} KB", candidate.size / 1024));
+    }
+
+    Some((score, reasons))
+}
+
+// `display_icon` is the DisplayIcon path without its icon index, already
+// mapped to the same file system as the candidates. Ties go to the earlier
+// candidate.
+pub fn select(candidates: &[Candidate], display_name: &str, display_icon: Option<&Path>) -> Option<Selection> {
+    let mut best: Option<Selection> = None;
+
+    for candidate in candidates {
+        let (score, reasons) = match score(candidate, display_name, display_icon) {
+            Some(scored) => scored,
+            None => continue,
+        };
+
+        if best.as_ref().is_none_or(|best| score > best.score) {
+            best = Some(Selection {
+                path: candidate.path.clone(),
+                score,
+                rationale: format!("score {} of {} candidates: {}", score, candidates.len(), reasons.join(", ")),
+            });
+        }
+    }
+
+    best
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn candidate(path: &str, size: u64, depth: usize) -> Candidate {
+        Candidate { path: PathBuf::from(path), size, depth }
+    }
+
+    fn selected(candidates: &[Candidate], display_name: &str, display_icon: Option<&str>) -> Option<String> {
+        select(candidates, display_name, display_icon.map(Path::new))
+            .map(|selection| selection.path.to_string_lossy().into_owned())
+    }
+
+    #[test]
+    fn skips_uninstallers_and_updaters() {
+        let candidates = [
+            candidate("/apps/Foo/unins000.exe", 4 << 20, 1),
+            candidate("/apps/Foo/Update.exe", 2 << 20, 1),
+            candidate("/apps/Foo/foo.exe", 1 << 20, 1),
+        ];
+
+        assert_eq!(selected(&candidates, "Foo", None).as_deref(), Some("/apps/Foo/foo.exe"));
+        assert_eq!(selected(&candidates[..2], "Foo", None), None);
+    }
+
+    #[test]
+    fn keeps_helpers_named_like_the_app() {
+        let candidates = [
+            candidate("/apps/SF/unins000.exe", 1 << 20, 1),
+            candidate("/apps/SF/SetupFactory.exe", 1 << 20, 1),
+        ];
+
+        assert_eq!(selected(&candidates, "Setup Factory 9", None).as_deref(), Some("/apps/SF/SetupFactory.exe"));
+    }
+
+    #[test]
+    fn display_icon_outweighs_name() {
+        let candidates = [
+            candidate("/apps/Foo/foo.exe", 8 << 20, 1),
+            candidate("/apps/Foo/bin/launcher.exe", 1 << 10, 2),
+        ];
+
+        assert_eq!(
+            selected(&candidates, "Foo", Some("/apps/Foo/bin/Launcher.EXE")).as_deref(),
+            Some("/apps/Foo/bin/launcher.exe"),
+        );
+    }
+
+    #[test]
+    fn name_similarity() {
+        let candidates = [
+            candidate("/apps/Studio/tool.exe", 1 << 20, 1),
+            candidate("/apps/Studio/studio64.exe", 1 << 20, 1),
+        ];
+
+        assert_eq!(selected(&candidates, "Foo Studio 2024", None).as_deref(), Some("/apps/Studio/studio64.exe"));
+    }
+
+    #[test]
+    fn shallower_candidate_wins_ties() {
+        let candidates = [
+            candidate("/apps/Foo/bin/foo.exe", 1 << 20, 2),
+            candidate("/apps/Foo/foo.exe", 1 << 20, 1),
+        ];
+
+        assert_eq!(selected(&candidates, "Foo", None).as_deref(), Some("/apps/Foo/foo.exe"));
+    }
+
+    #[test]
+    fn larger_candidate_wins_ties() {
+        let candidates = [
+            candidate("/apps/Foo/a.exe", 16 << 10, 1),
+            candidate("/apps/Foo/b.exe", 64 << 20, 1),
+        ];
+
+        assert_eq!(selected(&candidates, "Foo", None).as_deref(), Some("/apps/Foo/b.exe"));
+    }
+
+    #[test]
+    fn earlier_candidate_wins_exact_ties() {
+        let candidates = [
+            candidate("/apps/Foo/a.exe", 1 << 20, 1),
+            candidate("/apps/Foo/b.exe", 1 << 20, 1),
+        ];
+
+        assert_eq!(selected(&candidates, "Foo", None).as_deref(), Some("/apps/Foo/a.exe"));
+    }
+
+    #[test]
+    fn no_candidates() {
+        assert!(select(&[], "Foo", None).is_none());
+        assert!(select(&[], "Foo", Some(Path::new("/apps/Foo/foo.exe"))).is_none());
+    }
+
+    #[test]
+    fn rationale_lists_contributions() {
+        let candidates = [candidate("/apps/Foo/foo.exe", 1 << 20, 1)];
+        let selection = select(&candidates, "Foo", Some(Path::new("/apps/Foo/foo.exe"))).unwrap();
+
+        assert!(selection.rationale.contains("DisplayIcon +100"), "{}", selection.rationale);
+        assert!(selection.rationale.contains("name matches +50"), "{}", selection.rationale);
+        assert!(selection.rationale.starts_with("score "), "{}", selection.rationale);
+    }
+}
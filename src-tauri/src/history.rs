@@ -0,0 +1,226 @@
+use std::fs;
+use std::io::Write;
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use serde::{Serialize, Deserialize};
+
+use crate::config;
+
+const HISTORY_VERSION: u32 = 1;
+const HALF_LIFE_SECS: f64 = 14.0 * 24.0 * 60.0 * 60.0;
+const MAX_EVENTS_PER_ITEM: usize = 100;
+// Past ten half-lives an event weighs less than 0.1% and is dropped.
+const MAX_EVENT_AGE_SECS: u64 = (HALF_LIFE_SECS * 10.0) as u64;
+
+#[derive(Serialize, Deserialize, Default)]
+struct HistoryFile {
+    version: u32,
+    // Launch timestamps in Unix seconds, oldest first.
+    launches: HashMap<String, Vec<u64>>,
+}
+
+pub struct LaunchHistory {
+    path: PathBuf,
+    launches: HashMap<String, Vec<u64>>,
+}
+
+pub fn now_secs() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0)
+}
+
+pub fn history_path() -> PathBuf {
+    config::data_dir().join("launch_history.json")
+}
+
+// Version 0 is the unversioned map of launch counts the in-memory tracker
+// kept. Those launches have no timestamps, so they are dated to the migration.
+fn migrate(value: serde_json::Value, now: u64) -> Result<HistoryFile, String> {
+    let version = match value.get("version") {
+        Some(version) => version.as_u64().ok_or("invalid version")?,
+        None => 0,
+    };
+
+    match version {
+        0 => {
+            let counts: HashMap<String, u32> = serde_json::from_value(value).map_err(|e| e.to_string())?;
+            let launches = counts.into_iter()
+                .filter(|(_, count)| *count > 0)
+                .map(|(key, count)| (key, vec![now; (count as usize).min(MAX_EVENTS_PER_ITEM)]))
+                .collect();
+
+            Ok(HistoryFile { version: HISTORY_VERSION, launches })
+        }
+        1 => serde_json::from_value(value).map_err(|e| e.to_string()),
+        other => Err(format!("unsupported version {}", other)),
+    }
+}
+
+// Writes to a sibling temp file and renames it over the target, so a crash
+// mid-write leaves either the old or the new file, never a truncated one.
+pub fn write_atomic(path: &Path, data: &[u8]) -> std::io::Result<()> {
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+
+    let tmp_path = path.with_extension("tmp");
+    {
+        let mut file = fs::File::create(&tmp_path)?;
+        file.write_all(data)?;
+        file.sync_all()?;
+    }
+
+    fs::rename(&tmp_path, path)
+}
+
+impl LaunchHistory {
+    pub fn load(path: PathBuf) -> Self {
+        let launches = match fs::read_to_string(&path) {
+            Ok(content) => {
+                let parsed = serde_json::from_str::<serde_json::Value>(&content)
+                    .map_err(|e| e.to_string())
+                    .and_then(|value| migrate(value, now_secs()));
+
+                match parsed {
+                    Ok(file) => file.launches,
+                    Err(e) => {
+                        eprintln!("Failed to load launch history {}: {}", path.display(), e);
+                        let _ = fs::rename(&path, path.with_extension("json.bak"));
+                        HashMap::new()
+                    }
+                }
+            }
+            Err(_) => HashMap::new(),
+        };
+
+        Self {
+            path,
+            launches,
+        }
+    }
+
+    pub fn record_launch(&mut self, key: &str) {
+        let now = now_secs();
+
+        let events = self.launches.entry(key.to_string()).or_default();
+        events.push(now);
+        if events.len() > MAX_EVENTS_PER_ITEM {
+            let excess = events.len() - MAX_EVENTS_PER_ITEM;
+            events.drain(..excess);
+        }
+
+        self.prune(now);
+
+        if let Err(e) = self.save() {
+            eprintln!("Failed to save launch history: {}", e);
+        }
+    }
+
+    pub fn frecency(&self, key: &str) -> f64 {
+        self.launches
+            .get(key)
+            .map(|events| decayed_score(events, now_secs()))
+            .unwrap_or(0.0)
+    }
+
+    pub fn top(&self, limit: usize) -> Vec<(String, f64)> {
+        let now = now_secs();
+
+        let mut scored: Vec<(String, f64)> = self.launches
+            .iter()
+            .map(|(key, events)| (key.clone(), decayed_score(events, now)))
+            .filter(|(_, score)| *score > 0.0)
+            .collect();
+
+        scored.sort_by(|a, b| b.1.total_cmp(&a.1));
+        scored.truncate(limit);
+
+        scored
+    }
+
+    fn prune(&mut self, now: u64) {
+        let cutoff = now.saturating_sub(MAX_EVENT_AGE_SECS);
+
+        self.launches.retain(|_, events| {
+            events.retain(|&t| t >= cutoff);
+            !events.is_empty()
+        });
+    }
+
+    fn save(&self) -> std::io::Result<()> {
+        let file = HistoryFile {
+            version: HISTORY_VERSION,
+            launches: self.launches.clone(),
+        };
+        let data = serde_json::to_vec_pretty(&file)?;
+
+        write_atomic(&self.path, &data)
+    }
+}
+
+fn decayed_score(events: &[u64], now: u64) -> f64 {
+    events.iter()
+        .map(|&t| {
+            let age = now.saturating_sub(t) as f64;
+            0.5f64.powf(age / HALF_LIFE_SECS)
+        })
+        .sum()
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    const NOW: u64 = 1_700_000_000;
+    const DAY: u64 = 24 * 60 * 60;
+
+    #[test]
+    fn launches_decay_with_a_two_week_half_life() {
+        let cases: &[(&[u64], f64)] = &[
+            (&[NOW], 1.0),
+            (&[NOW - 14 * DAY], 0.5),
+            (&[NOW - 28 * DAY], 0.25),
+            (&[NOW, NOW - 14 * DAY, NOW - 28 * DAY], 1.75),
+            // Clock skew never makes a launch count more than once.
+            (&[NOW + DAY], 1.0),
+            (&[], 0.0),
+        ];
+
+        for (events, expected) in cases {
+            let score = decayed_score(events, NOW);
+            assert!((score - expected).abs() < 1e-9, "events {:?} scored {}, expected {}", events, score, expected);
+        }
+    }
+
+    #[test]
+    fn migrates_unversioned_launch_counts() {
+        let baseline = json!({
+            "/usr/share/applications/gimp.desktop": 3,
+            "/usr/share/applications/vim.desktop": 0,
+            "C:\\Program Files\\App\\app.exe": 500,
+        });
+
+        let file = migrate(baseline, NOW).unwrap();
+
+        assert_eq!(file.version, HISTORY_VERSION);
+        assert_eq!(file.launches.len(), 2, "apps never launched are dropped");
+        assert_eq!(file.launches["/usr/share/applications/gimp.desktop"], [NOW; 3]);
+        assert_eq!(file.launches["C:\\Program Files\\App\\app.exe"].len(), MAX_EVENTS_PER_ITEM);
+    }
+
+    #[test]
+    fn migrates_versioned_files() {
+        let current = json!({ "version": 1, "launches": { "/usr/bin/htop": [NOW - DAY, NOW] } });
+        assert_eq!(migrate(current, NOW).unwrap().launches["/usr/bin/htop"], [NOW - DAY, NOW]);
+
+        assert!(migrate(json!({ "version": 2, "launches": {} }), NOW).is_err());
+        assert!(migrate(json!({ "version": "1" }), NOW).is_err());
+        assert!(migrate(json!({ "/usr/bin/htop": "often" }), NOW).is_err());
+    }
+}
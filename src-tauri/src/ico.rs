@@ -0,0 +1,198 @@
+use std::fs;
+use std::path::Path;
+
+use image::{Rgba, RgbaImage};
+
+// One image of a multi-resolution icon, as stored in RT_ICON resources and
+// .ico files: either a PNG stream or a headerless DIB (BITMAPINFOHEADER,
+// palette, XOR pixels and a 1-bit AND mask, with the height doubled).
+pub struct IconImage {
+    pub width: u32,
+    pub height: u32,
+    pub bit_count: u16,
+    pub data: Vec<u8>,
+}
+
+const PNG_SIGNATURE: &[u8] = b"\x89PNG\r\n\x1a\n";
+
+// Directory entries store 256 as 0.
+pub fn entry_dimension(value: u8) -> u32 {
+    if value == 0 { 256 } else { value as u32 }
+}
+
+// Prefers an exact match, then the smallest image larger than `size` (so it
+// can be scaled down), then the largest smaller one. Ties go to the image
+// with more colors.
+pub fn best_image(images: &[IconImage], size: u32) -> Option<&IconImage> {
+    images.iter().min_by_key(|image| {
+        let dimension = image.width.max(image.height);
+        let rank = if dimension == size {
+            (0, 0)
+        } else if dimension > size {
+            (1, dimension - size)
+        } else {
+            (2, size - dimension)
+        };
+        (rank, std::cmp::Reverse(image.bit_count))
+    })
+}
+
+pub fn decode(data: &[u8]) -> Option<RgbaImage> {
+    if data.starts_with(PNG_SIGNATURE) {
+        return image::load_from_memory_with_format(data, image::ImageFormat::Png)
+            .ok()
+            .map(|img| img.to_rgba8());
+    }
+
+    decode_dib(data)
+}
+
+// Reads the images of a standalone .ico or .cur file. Cursors reuse the
+// planes/bit count fields for the hotspot, so the depth is taken from the
+// image itself there (and whenever the directory leaves it at zero).
+pub fn parse_icon_file(data: &[u8]) -> Result<Vec<IconImage>, String> {
+    let kind = u16_at(data, 2).ok_or("truncated icon header")?;
+    if u16_at(data, 0) != Some(0) || !(kind == 1 || kind == 2) {
+        return Err("not an icon file".to_string());
+    }
+    let count = u16_at(data, 4).ok_or("truncated icon header")? as usize;
+
+    let images = (0..count)
+        .filter_map(|i| {
+            let entry = data.get(6 + i * 16..6 + (i + 1) * 16)?;
+            let size = u32_at(entry, 8)? as usize;
+            let offset = u32_at(entry, 12)? as usize;
+            let image = data.get(offset..offset.checked_add(size)?)?;
+
+            let bit_count = match u16_at(entry, 6)? {
+                depth if kind == 1 && depth != 0 => depth,
+                _ if image.starts_with(PNG_SIGNATURE) => 32,
+                _ => u16_at(image, 14)?,
+            };
+
+            Some(IconImage {
+                width: entry_dimension(entry[0]),
+                height: entry_dimension(entry[1]),
+                bit_count,
+                data: image.to_vec(),
+            })
+        })
+        .collect::<Vec<_>>();
+
+    if images.is_empty() {
+        return Err("icon file has no images".to_string());
+    }
+
+    Ok(images)
+}
+
+pub fn is_icon_file(path: &Path) -> bool {
+    path.extension()
+        .and_then(|ext| ext.to_str())
+        .is_some_and(|ext| ext.eq_ignore_ascii_case("ico") || ext.eq_ignore_ascii_case("cur"))
+}
+
+pub fn load_icon_file(path: &Path, size: u32) -> Option<RgbaImage> {
+    let data = fs::read(path).ok()?;
+    let images = parse_icon_file(&data).ok()?;
+
+    decode(&best_image(&images, size)?.data)
+}
+
+fn u16_at(data: &[u8], offset: usize) -> Option<u16> {
+    Some(u16::from_le_bytes(data.get(offset..offset + 2)?.try_into().ok()?))
+}
+
+fn u32_at(data: &[u8], offset: usize) -> Option<u32> {
+    Some(u32::from_le_bytes(data.get(offset..offset + 4)?.try_into().ok()?))
+}
+
+fn decode_dib(data: &[u8]) -> Option<RgbaImage> {
+    let header_size = u32_at(data, 0)? as usize;
+    let width = u32_at(data, 4)? as i32;
+    let height = u32_at(data, 8)? as i32;
+    let bit_count = u16_at(data, 14)?;
+    let colors_used = u32_at(data, 32)? as usize;
+
+    // The stored height covers both the XOR image and the AND mask.
+    let width = width.unsigned_abs();
+    let height = height.unsigned_abs() / 2;
+    if width == 0 || height == 0 || width > 1024 || height > 1024 {
+        return None;
+    }
+
+    let palette_len = match bit_count {
+        1 | 4 | 8 if colors_used > 0 => colors_used,
+        1 | 4 | 8 => 1 << bit_count,
+        16 | 24 | 32 => 0,
+        _ => return None,
+    };
+    // Header fields are untrusted; offsets that overflow mean a damaged image.
+    let palette_offset = header_size;
+    let xor_offset = palette_len.checked_mul(4)?.checked_add(palette_offset)?;
+    let palette = data.get(palette_offset..xor_offset)?;
+
+    let xor_stride = (width as usize * bit_count as usize).div_ceil(32) * 4;
+    let and_offset = xor_stride.checked_mul(height as usize)?.checked_add(xor_offset)?;
+    let and_stride = (width as usize).div_ceil(32) * 4;
+
+    let mut image = RgbaImage::new(width, height);
+    let mut has_alpha = false;
+
+    for y in 0..height as usize {
+        // DIB rows are stored bottom-up.
+        let row_offset = xor_offset + (height as usize - 1 - y) * xor_stride;
+        let row = data.get(row_offset..row_offset + xor_stride)?;
+
+        for x in 0..width as usize {
+            let indexed = |bits: usize| -> Option<[u8; 4]> {
+                let bit = x * bits;
+                let byte = row[bit / 8];
+                let shift = 8 - bits - bit % 8;
+                let index = ((byte >> shift) as usize) & ((1 << bits) - 1);
+                let color = palette.get(index * 4..index * 4 + 4)?;
+                Some([color[2], color[1], color[0], 255])
+            };
+
+            let pixel = match bit_count {
+                1 | 4 | 8 => indexed(bit_count as usize)?,
+                16 => {
+                    let value = u16::from_le_bytes([row[x * 2], row[x * 2 + 1]]);
+                    let channel = |shift: u16| (((value >> shift) & 0x1F) as u8) << 3;
+                    [channel(10), channel(5), channel(0), 255]
+                }
+                24 => [row[x * 3 + 2], row[x * 3 + 1], row[x * 3], 255],
+                _ => {
+                    let alpha = row[x * 4 + 3];
+                    has_alpha |= alpha != 0;
+                    [row[x * 4 + 2], row[x * 4 + 1], row[x * 4], alpha]
+                }
+            };
+
+            image.put_pixel(x as u32, y as u32, Rgba(pixel));
+        }
+    }
+
+    // 32-bit images carry their own alpha; older ones (and 32-bit images
+    // whose alpha is all zero) rely on the AND mask, where 1 is transparent.
+    if !has_alpha {
+        let mask = and_offset.checked_add(and_stride * height as usize)
+            .and_then(|and_end| data.get(and_offset..and_end));
+
+        for y in 0..height as usize {
+            for x in 0..width as usize {
+                let transparent = match mask {
+                    Some(mask) => {
+                        let row = &mask[(height as usize - 1 - y) * and_stride..];
+                        row[x / 8] & (0x80 >> (x % 8)) != 0
+                    }
+                    None => false,
+                };
+
+                image.get_pixel_mut(x as u32, y as u32).0[3] = if transparent { 0 } else { 255 };
+            }
+        }
+    }
+
+    Some(image)
+}
@@ -0,0 +1,382 @@
+use std::fs;
+use std::sync::{Mutex, OnceLock};
+use std::collections::{HashMap, HashSet};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use image::DynamicImage;
+use serde::{Serialize, Deserialize};
+
+use crate::{config, ico, pe_icon};
+use crate::history::write_atomic;
+
+const INDEX_VERSION: u32 = 1;
+const INDEX_FILE: &str = "index.json";
+const DEFAULT_ICON_FILE: &str = "default_icon.png";
+// Renders run concurrently with garbage collection; files this young may be
+// a `.tmp` still being written or a PNG not yet entered in the index.
+const STRAY_GRACE_PERIOD: Duration = Duration::from_secs(60);
+
+static ICON_CACHE: OnceLock<IconCache> = OnceLock::new();
+static TMP_ID: AtomicU64 = AtomicU64::new(1);
+
+#[derive(Serialize, Deserialize, Clone)]
+struct IconEntry {
+    file: String,
+    source: String,
+    size: u64,
+    last_used: u64,
+}
+
+#[derive(Serialize, Deserialize, Default)]
+struct IconIndex {
+    version: u32,
+    entries: HashMap<String, IconEntry>,
+}
+
+pub struct IconCache {
+    dir: PathBuf,
+    index: Mutex<IconIndex>,
+    dirty: Mutex<bool>,
+}
+
+pub fn global() -> &'static IconCache {
+    ICON_CACHE.get_or_init(|| IconCache::open(icon_cache_dir()))
+}
+
+fn icon_cache_dir() -> PathBuf {
+    dirs::cache_dir()
+        .unwrap_or_else(|| PathBuf::from("."))
+        .join("bsearch")
+        .join("icons")
+}
+
+fn now_secs() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0)
+}
+
+// FNV-1a; std's hashers are not guaranteed stable across releases, and these
+// keys are persisted.
+fn fnv1a(data: &[u8], mut hash: u64) -> u64 {
+    for byte in data {
+        hash ^= *byte as u64;
+        hash = hash.wrapping_mul(0x100000001b3);
+    }
+    hash
+}
+
+// Identifies a rendered icon by where it came from: the source file, its
+// modification time and size, and the variant (size/index) that was rendered.
+pub fn icon_key(source: &Path, variant: &str) -> Option<String> {
+    let metadata = fs::metadata(source).ok()?;
+    let mtime = metadata.modified()
+        .ok()
+        .and_then(|t| t.duration_since(UNIX_EPOCH).ok())
+        .map(|d| d.as_nanos())
+        .unwrap_or(0);
+
+    let mut hash = 0xcbf29ce484222325;
+    hash = fnv1a(source.to_string_lossy().as_bytes(), hash);
+    hash = fnv1a(&mtime.to_le_bytes(), hash);
+    hash = fnv1a(&metadata.len().to_le_bytes(), hash);
+    hash = fnv1a(variant.as_bytes(), hash);
+
+    Some(format!("{:016x}", hash))
+}
+
+impl IconCache {
+    fn open(dir: PathBuf) -> Self {
+        if let Err(e) = fs::create_dir_all(&dir) {
+            eprintln!("Failed to create icon cache directory: {}", e);
+        }
+
+        let index = fs::read_to_string(dir.join(INDEX_FILE))
+            .ok()
+            .and_then(|content| serde_json::from_str::<IconIndex>(&content).ok())
+            .filter(|index| index.version == INDEX_VERSION)
+            .unwrap_or_default();
+
+        Self {
+            dir,
+            index: Mutex::new(index),
+            dirty: Mutex::new(false),
+        }
+    }
+
+    // Returns the cached PNG for `source`/`variant`, calling `render` to
+    // produce it only on a miss. `render` writes a PNG to the path it is given.
+    pub fn get_or_insert_with<F>(&self, source: &Path, variant: &str, render: F) -> Option<String>
+    where
+        F: FnOnce(&Path) -> bool,
+    {
+        let key = icon_key(source, variant)?;
+        let file_name = format!("{}.png", key);
+        let icon_path = self.dir.join(&file_name);
+
+        {
+            let mut index = self.index.lock().unwrap();
+            if let Some(entry) = index.entries.get_mut(&key) {
+                if icon_path.exists() {
+                    entry.last_used = now_secs();
+                    *self.dirty.lock().unwrap() = true;
+                    return Some(icon_path.to_string_lossy().to_string());
+                }
+            }
+        }
+
+        // Render to a private temp file so concurrent misses for the same
+        // key never interleave writes to the final path.
+        let tmp_path = self.dir.join(format!("{}.{}.tmp", key, TMP_ID.fetch_add(1, Ordering::SeqCst)));
+        if !render(&tmp_path) {
+            let _ = fs::remove_file(&tmp_path);
+            return None;
+        }
+        if fs::rename(&tmp_path, &icon_path).is_err() {
+            let _ = fs::remove_file(&tmp_path);
+            return None;
+        }
+
+        let size = fs::metadata(&icon_path).map(|m| m.len()).unwrap_or(0);
+
+        self.index.lock().unwrap().entries.insert(key, IconEntry {
+            file: file_name,
+            source: source.to_string_lossy().to_string(),
+            size,
+            last_used: now_secs(),
+        });
+        *self.dirty.lock().unwrap() = true;
+
+        Some(icon_path.to_string_lossy().to_string())
+    }
+
+    pub fn default_icon(&self) -> Option<String> {
+        let default_icon_path = self.dir.join(DEFAULT_ICON_FILE);
+
+        if !default_icon_path.exists() {
+            create_default_icon(&default_icon_path);
+        }
+
+        if default_icon_path.exists() {
+            Some(default_icon_path.to_string_lossy().to_string())
+        } else {
+            None
+        }
+    }
+
+    // Drops icons no indexed app points at, then evicts least recently used
+    // ones until the cache fits in `max_bytes`. Also sweeps stray files left
+    // behind by older versions or interrupted renders.
+    pub fn collect_garbage(&self, referenced: &HashSet<String>, max_bytes: u64) {
+        let mut index = self.index.lock().unwrap();
+
+        let dir = self.dir.clone();
+        let is_referenced = |entry: &IconEntry| {
+            referenced.contains(dir.join(&entry.file).to_string_lossy().as_ref())
+        };
+
+        index.entries.retain(|_, entry| {
+            let keep = is_referenced(entry);
+            if !keep {
+                let _ = fs::remove_file(dir.join(&entry.file));
+            }
+            keep
+        });
+
+        let mut total: u64 = index.entries.values().map(|e| e.size).sum();
+        if total > max_bytes {
+            let mut by_age: Vec<(String, u64, u64)> = index.entries
+                .iter()
+                .map(|(key, entry)| (key.clone(), entry.last_used, entry.size))
+                .collect();
+            by_age.sort_by_key(|(_, last_used, _)| *last_used);
+
+            for (key, _, size) in by_age {
+                if total <= max_bytes {
+                    break;
+                }
+                if let Some(entry) = index.entries.remove(&key) {
+                    let _ = fs::remove_file(dir.join(&entry.file));
+                    total = total.saturating_sub(size);
+                }
+            }
+        }
+
+        let known: HashSet<&str> = index.entries.values().map(|e| e.file.as_str()).collect();
+        if let Ok(read_dir) = fs::read_dir(&self.dir) {
+            for entry in read_dir.filter_map(|e| e.ok()) {
+                let name = entry.file_name().to_string_lossy().to_string();
+                if name == INDEX_FILE || name == DEFAULT_ICON_FILE || known.contains(name.as_str()) {
+                    continue;
+                }
+                let in_flight = entry.metadata()
+                    .and_then(|m| m.modified())
+                    .ok()
+                    .and_then(|modified| modified.elapsed().ok())
+                    .is_none_or(|age| age < STRAY_GRACE_PERIOD);
+                if in_flight {
+                    continue;
+                }
+                let _ = fs::remove_file(entry.path());
+            }
+        }
+
+        *self.dirty.lock().unwrap() = true;
+    }
+
+    pub fn flush(&self) {
+        let mut dirty = self.dirty.lock().unwrap();
+        if !*dirty {
+            return;
+        }
+
+        let data = {
+            let index = self.index.lock().unwrap();
+            let file = IconIndex {
+                version: INDEX_VERSION,
+                entries: index.entries.clone(),
+            };
+            serde_json::to_vec(&file)
+        };
+
+        let result = data
+            .map_err(std::io::Error::from)
+            .and_then(|data| write_atomic(&self.dir.join(INDEX_FILE), &data));
+
+        match result {
+            Ok(()) => *dirty = false,
+            Err(e) => eprintln!("Failed to save icon index: {}", e),
+        }
+    }
+}
+
+// Writes `source` as a PNG no larger than `size` pixels on its longest side.
+// SVGs are rasterized at exactly that size; .ico/.cur files and PE files
+// (.exe, .dll) contribute their best image for the size; other formats go
+// through `image`.
+pub fn render_icon_file(source: &Path, size: u32, out: &Path) -> bool {
+    render_icon_resource(source, 0, size, out)
+}
+
+// Like `render_icon_file`, with `index` selecting the icon group in PE files.
+pub fn render_icon_resource(source: &Path, index: i32, size: u32, out: &Path) -> bool {
+    if is_svg(source) {
+        return render_svg(source, size, out);
+    }
+
+    let img = if pe_icon::is_pe_file(source) {
+        pe_icon::load_icon(source, index, size).map(DynamicImage::ImageRgba8)
+    } else if ico::is_icon_file(source) {
+        ico::load_icon_file(source, size).map(DynamicImage::ImageRgba8)
+    } else {
+        image::open(source).ok()
+    };
+
+    match img {
+        Some(img) => save_scaled(img, size, out),
+        None => false,
+    }
+}
+
+// Renders an icon read from somewhere other than a plain file, such as an
+// archive. SVG (and gzipped SVG) is recognized by content; anything else goes
+// through `image`.
+#[cfg(target_os = "linux")]
+pub fn render_icon_data(data: &[u8], name: &str, size: u32, out: &Path) -> bool {
+    let head = String::from_utf8_lossy(&data[..data.len().min(512)]);
+    if data.starts_with(&[0x1F, 0x8B]) || (head.trim_start().starts_with('<') && head.contains("<svg")) {
+        return render_svg_data(data, None, name, size, out);
+    }
+
+    match image::load_from_memory(data) {
+        Ok(img) => save_scaled(img, size, out),
+        Err(_) => false,
+    }
+}
+
+fn save_scaled(img: DynamicImage, size: u32, out: &Path) -> bool {
+    let img = if img.width() > size || img.height() > size {
+        img.resize(size, size, image::imageops::FilterType::Lanczos3)
+    } else {
+        img
+    };
+
+    img.save_with_format(out, image::ImageFormat::Png).is_ok()
+}
+
+pub fn is_svg(path: &Path) -> bool {
+    path.extension()
+        .and_then(|ext| ext.to_str())
+        .is_some_and(|ext| ext.eq_ignore_ascii_case("svg") || ext.eq_ignore_ascii_case("svgz"))
+}
+
+fn render_svg(source: &Path, size: u32, out: &Path) -> bool {
+    match fs::read(source) {
+        Ok(data) => render_svg_data(&data, source.parent(), &source.display().to_string(), size, out),
+        Err(_) => false,
+    }
+}
+
+// Scales the drawing to fit a `size`x`size` square, centered, keeping its
+// aspect ratio. `name` is only used in error messages.
+fn render_svg_data(data: &[u8], resources_dir: Option<&Path>, name: &str, size: u32, out: &Path) -> bool {
+    use resvg::{tiny_skia, usvg};
+
+    let options = usvg::Options {
+        resources_dir: resources_dir.map(Path::to_path_buf),
+        ..Default::default()
+    };
+
+    let tree = match usvg::Tree::from_data(data, &options) {
+        Ok(tree) => tree,
+        Err(e) => {
+            eprintln!("Failed to parse SVG {}: {}", name, e);
+            return false;
+        }
+    };
+
+    let mut pixmap = match tiny_skia::Pixmap::new(size, size) {
+        Some(pixmap) => pixmap,
+        None => return false,
+    };
+
+    let (width, height) = (tree.size().width(), tree.size().height());
+    let scale = size as f32 / width.max(height);
+    let transform = tiny_skia::Transform::from_scale(scale, scale).post_translate(
+        (size as f32 - width * scale) / 2.0,
+        (size as f32 - height * scale) / 2.0,
+    );
+
+    resvg::render(&tree, transform, &mut pixmap.as_mut());
+
+    pixmap.save_png(out).is_ok()
+}
+
+pub fn max_cache_bytes() -> u64 {
+    config::get().icon_cache_max_mb.saturating_mul(1024 * 1024)
+}
+
+fn create_default_icon(path: &Path) -> bool {
+    use image::{ImageBuffer, Rgba};
+
+    let mut img = ImageBuffer::<Rgba<u8>, _>::new(48, 48);
+
+    for y in 0..48 {
+        for x in 0..48 {
+            let dx = x as i32 - 24;
+            let dy = y as i32 - 24;
+            let color = if dx * dx + dy * dy < 20 * 20 {
+                Rgba([100, 149, 237, 255])
+            } else {
+                Rgba([0, 0, 0, 0])
+            };
+
+            img.put_pixel(x, y, color);
+        }
+    }
+
+    img.save(path).is_ok()
+}
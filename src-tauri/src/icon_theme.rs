@@ -0,0 +1,274 @@
+use std::env;
+use std::fs;
+use std::collections::HashSet;
+use std::path::{Path, PathBuf};
+
+use crate::{config, sandboxed_apps};
+use crate::desktop_entry::parse_groups;
+
+// XPM is allowed by the spec but `image` can't decode it, so such icons
+// would only ever render as the default icon; looking further finds a
+// usable one if the theme has one.
+const ICON_EXTENSIONS: &[&str] = &["png", "svg"];
+const FALLBACK_THEME: &str = "hicolor";
+
+#[derive(Clone, Copy, PartialEq)]
+enum DirKind {
+    Fixed,
+    Scalable,
+    Threshold,
+}
+
+struct ThemeDir {
+    size: u32,
+    scale: u32,
+    min_size: u32,
+    max_size: u32,
+    threshold: u32,
+    kind: DirKind,
+    // Every `<base>/<theme>/<subdir>` that exists, with its file names read
+    // up front so lookups don't stat hundreds of paths per icon.
+    locations: Vec<(PathBuf, HashSet<String>)>,
+}
+
+impl ThemeDir {
+    // DirectoryMatchesSize from the Icon Theme Specification.
+    fn matches(&self, size: u32, scale: u32) -> bool {
+        if self.scale != scale {
+            return false;
+        }
+
+        match self.kind {
+            DirKind::Fixed => self.size == size,
+            DirKind::Scalable => self.min_size <= size && size <= self.max_size,
+            DirKind::Threshold => {
+                self.size.saturating_sub(self.threshold) <= size && size <= self.size + self.threshold
+            }
+        }
+    }
+
+    // DirectorySizeDistance from the Icon Theme Specification.
+    fn distance(&self, size: u32, scale: u32) -> u32 {
+        let wanted = size * scale;
+
+        let (low, high) = match self.kind {
+            DirKind::Fixed => (self.size, self.size),
+            DirKind::Scalable => (self.min_size, self.max_size),
+            DirKind::Threshold => (self.size.saturating_sub(self.threshold), self.size + self.threshold),
+        };
+
+        (low * self.scale).saturating_sub(wanted) + wanted.saturating_sub(high * self.scale)
+    }
+
+    fn find(&self, icon_name: &str) -> Option<PathBuf> {
+        self.locations.iter().find_map(|(dir, files)| {
+            ICON_EXTENSIONS.iter()
+                .map(|ext| format!("{}.{}", icon_name, ext))
+                .find(|file| files.contains(file))
+                .map(|file| dir.join(file))
+        })
+    }
+}
+
+struct Theme {
+    parents: Vec<String>,
+    dirs: Vec<ThemeDir>,
+}
+
+impl Theme {
+    fn load(name: &str, base_dirs: &[PathBuf]) -> Option<Self> {
+        let index_path = base_dirs.iter()
+            .map(|base| base.join(name).join("index.theme"))
+            .find(|path| path.is_file())?;
+
+        let mut groups = parse_groups(&fs::read_to_string(index_path).ok()?);
+        let header = groups.remove("Icon Theme")?;
+
+        let split = |value: Option<&String>| -> Vec<String> {
+            value.map(|v| {
+                v.split(',')
+                    .map(|s| s.trim().to_string())
+                    .filter(|s| !s.is_empty())
+                    .collect()
+            }).unwrap_or_default()
+        };
+
+        let parents = split(header.get("Inherits"));
+        let mut subdirs = split(header.get("Directories"));
+        subdirs.extend(split(header.get("ScaledDirectories")));
+
+        let dirs = subdirs.iter()
+            .filter_map(|subdir| {
+                let group = groups.get(subdir)?;
+                let number = |key: &str| group.get(key).and_then(|v| v.parse::<u32>().ok());
+
+                let size = number("Size")?;
+                let kind = match group.get("Type").map(|t| t.as_str()) {
+                    Some("Fixed") => DirKind::Fixed,
+                    Some("Scalable") => DirKind::Scalable,
+                    _ => DirKind::Threshold,
+                };
+
+                let locations = base_dirs.iter()
+                    .map(|base| base.join(name).join(subdir))
+                    .filter_map(|dir| {
+                        let files = fs::read_dir(&dir).ok()?
+                            .filter_map(|e| e.ok())
+                            .map(|e| e.file_name().to_string_lossy().to_string())
+                            .collect();
+                        Some((dir, files))
+                    })
+                    .collect();
+
+                Some(ThemeDir {
+                    size,
+                    scale: number("Scale").unwrap_or(1),
+                    min_size: number("MinSize").unwrap_or(size),
+                    max_size: number("MaxSize").unwrap_or(size),
+                    threshold: number("Threshold").unwrap_or(2),
+                    kind,
+                    locations,
+                })
+            })
+            .collect();
+
+        Some(Self { parents, dirs })
+    }
+
+    // LookupIcon: an exact size match anywhere in the theme wins, otherwise
+    // the closest size this theme has.
+    fn lookup(&self, icon_name: &str, size: u32, scale: u32) -> Option<PathBuf> {
+        for dir in self.dirs.iter().filter(|dir| dir.matches(size, scale)) {
+            if let Some(path) = dir.find(icon_name) {
+                return Some(path);
+            }
+        }
+
+        self.dirs.iter()
+            .filter_map(|dir| dir.find(icon_name).map(|path| (dir.distance(size, scale), path)))
+            .min_by_key(|(distance, _)| *distance)
+            .map(|(_, path)| path)
+    }
+}
+
+pub struct IconLookup {
+    // The selected theme and its inheritance chain in lookup order, with
+    // hicolor always last.
+    themes: Vec<Theme>,
+    fallback_dirs: Vec<PathBuf>,
+}
+
+fn icon_base_dirs() -> Vec<PathBuf> {
+    let mut base_dirs = Vec::new();
+
+    if let Some(home) = dirs::home_dir() {
+        base_dirs.push(home.join(".icons"));
+    }
+
+    let data_home = env::var("XDG_DATA_HOME")
+        .ok()
+        .filter(|v| !v.is_empty())
+        .map(PathBuf::from)
+        .or_else(|| dirs::home_dir().map(|home| home.join(".local/share")));
+    if let Some(data_home) = data_home {
+        base_dirs.push(data_home.join("icons"));
+    }
+
+    let data_dirs = env::var("XDG_DATA_DIRS")
+        .ok()
+        .filter(|v| !v.is_empty())
+        .unwrap_or_else(|| "/usr/local/share:/usr/share".to_string());
+    for dir in data_dirs.split(':').filter(|d| !d.is_empty()) {
+        base_dirs.push(PathBuf::from(dir).join("icons"));
+    }
+
+    for dir in sandboxed_apps::export_dirs() {
+        let dir = dir.join("icons");
+        if !base_dirs.contains(&dir) {
+            base_dirs.push(dir);
+        }
+    }
+
+    base_dirs
+}
+
+fn read_setting(path: &Path, group: &str, key: &str) -> Option<String> {
+    let content = fs::read_to_string(path).ok()?;
+    parse_groups(&content)
+        .get(group)?
+        .get(key)
+        .map(|v| v.trim_matches('"').to_string())
+        .filter(|v| !v.is_empty())
+}
+
+fn current_theme_name() -> String {
+    if let Some(theme) = &config::get().icon_theme {
+        return theme.clone();
+    }
+
+    let config_home = dirs::config_dir().unwrap_or_else(|| PathBuf::from("."));
+
+    read_setting(&config_home.join("gtk-4.0/settings.ini"), "Settings", "gtk-icon-theme-name")
+        .or_else(|| read_setting(&config_home.join("gtk-3.0/settings.ini"), "Settings", "gtk-icon-theme-name"))
+        .or_else(|| read_setting(&config_home.join("kdeglobals"), "Icons", "Theme"))
+        .unwrap_or_else(|| FALLBACK_THEME.to_string())
+}
+
+impl IconLookup {
+    pub fn load() -> Self {
+        let base_dirs = icon_base_dirs();
+
+        let mut themes = Vec::new();
+        let mut visited = HashSet::new();
+        let mut pending = vec![current_theme_name()];
+
+        // Depth-first over Inherits, matching FindIconHelper's recursion.
+        while let Some(name) = pending.pop() {
+            if name == FALLBACK_THEME || !visited.insert(name.clone()) {
+                continue;
+            }
+
+            if let Some(theme) = Theme::load(&name, &base_dirs) {
+                pending.extend(theme.parents.iter().rev().cloned());
+                themes.push(theme);
+            }
+        }
+
+        if let Some(hicolor) = Theme::load(FALLBACK_THEME, &base_dirs) {
+            themes.push(hicolor);
+        }
+
+        let mut fallback_dirs = base_dirs.clone();
+        fallback_dirs.push(PathBuf::from("/usr/share/pixmaps"));
+
+        Self {
+            themes,
+            fallback_dirs,
+        }
+    }
+
+    // Resolves an `Icon=` value to a file: absolute paths are used as is,
+    // names go through the theme chain and then the unthemed fallback dirs.
+    pub fn find_icon(&self, icon: &str, size: u32, scale: u32) -> Option<PathBuf> {
+        let icon_path = Path::new(icon);
+        if icon_path.is_absolute() {
+            return icon_path.is_file().then(|| icon_path.to_path_buf());
+        }
+
+        // Some entries still carry the extension, which the spec says to ignore.
+        let icon_name = ICON_EXTENSIONS.iter()
+            .chain(&["xpm"])
+            .find_map(|ext| icon.strip_suffix(&format!(".{}", ext)))
+            .unwrap_or(icon);
+
+        self.themes.iter()
+            .find_map(|theme| theme.lookup(icon_name, size, scale))
+            .or_else(|| {
+                self.fallback_dirs.iter().find_map(|dir| {
+                    ICON_EXTENSIONS.iter()
+                        .map(|ext| dir.join(format!("{}.{}", icon_name, ext)))
+                        .find(|path| path.is_file())
+                })
+            })
+    }
+}
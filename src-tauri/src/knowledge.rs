@@ -0,0 +1,249 @@
+use std::fs;
+use std::collections::HashMap;
+use std::path::PathBuf;
+
+use serde::{Serialize, Deserialize};
+
+use crate::{config, normalize};
+use crate::history::{now_secs, write_atomic};
+
+const KNOWLEDGE_VERSION: u32 = 1;
+const MAX_QUERY_CHARS: usize = 32;
+const MAX_COUNTED_SELECTIONS: u32 = 5;
+// Picks fade so an association stops steering results once the user moves on.
+const HALF_LIFE_SECS: f64 = 30.0 * 24.0 * 60.0 * 60.0;
+// Past ten half-lives a selection weighs less than 0.1% and is dropped.
+const MAX_SELECTION_AGE_SECS: u64 = (HALF_LIFE_SECS * 10.0) as u64;
+
+#[derive(Serialize, Deserialize, Clone, Copy)]
+struct Selection {
+    count: u32,
+    last_used: u64,
+}
+
+#[derive(Serialize, Deserialize)]
+struct KnowledgeFile {
+    version: u32,
+    // Normalized query -> result key -> how often it was picked for that query.
+    queries: HashMap<String, HashMap<String, Selection>>,
+}
+
+pub struct Knowledge {
+    path: PathBuf,
+    queries: HashMap<String, HashMap<String, Selection>>,
+}
+
+pub fn knowledge_path() -> PathBuf {
+    config::data_dir().join("knowledge.json")
+}
+
+fn migrate(value: serde_json::Value) -> Result<KnowledgeFile, String> {
+    let version = value.get("version")
+        .and_then(|v| v.as_u64())
+        .ok_or("missing version")?;
+
+    match version {
+        1 => serde_json::from_value(value).map_err(|e| e.to_string()),
+        other => Err(format!("unsupported version {}", other)),
+    }
+}
+
+fn decay(selection: &Selection, now: u64) -> f64 {
+    let age = now.saturating_sub(selection.last_used) as f64;
+    0.5f64.powf(age / HALF_LIFE_SECS)
+}
+
+fn normalize_query(query: &str) -> String {
+    normalize::fold(query.trim())
+        .chars()
+        .take(MAX_QUERY_CHARS)
+        .collect()
+}
+
+impl Knowledge {
+    pub fn load(path: PathBuf) -> Self {
+        let queries = match fs::read_to_string(&path) {
+            Ok(content) => {
+                let parsed = serde_json::from_str::<serde_json::Value>(&content)
+                    .map_err(|e| e.to_string())
+                    .and_then(migrate);
+
+                match parsed {
+                    Ok(file) => file.queries,
+                    Err(e) => {
+                        eprintln!("Failed to load knowledge {}: {}", path.display(), e);
+                        let _ = fs::rename(&path, path.with_extension("json.bak"));
+                        HashMap::new()
+                    }
+                }
+            }
+            Err(_) => HashMap::new(),
+        };
+
+        Self { path, queries }
+    }
+
+    pub fn record(&mut self, query: &str, key: &str) {
+        self.record_at(query, key, now_secs());
+    }
+
+    fn record_at(&mut self, query: &str, key: &str, now: u64) {
+        let query = normalize_query(query);
+        if query.is_empty() {
+            return;
+        }
+
+        let selection = self.queries
+            .entry(query)
+            .or_default()
+            .entry(key.to_string())
+            .or_insert(Selection { count: 0, last_used: now });
+        selection.count += 1;
+        selection.last_used = now;
+
+        self.prune(now);
+        self.save();
+    }
+
+    // Every stored query that is a prefix of (or equal to) the current one
+    // contributes, weighted by how much of the current query it covers.
+    pub fn boost(&self, query: &str, key: &str) -> f64 {
+        self.boost_at(query, key, now_secs())
+    }
+
+    fn boost_at(&self, query: &str, key: &str, now: u64) -> f64 {
+        let query = normalize_query(query);
+        if query.is_empty() {
+            return 0.0;
+        }
+
+        let query_len = query.chars().count() as f64;
+
+        self.queries.iter()
+            .filter(|(learned, _)| query.starts_with(learned.as_str()))
+            .filter_map(|(learned, selections)| {
+                let selection = selections.get(key)?;
+                let coverage = learned.chars().count() as f64 / query_len;
+                Some(coverage * selection.count.min(MAX_COUNTED_SELECTIONS) as f64 * decay(selection, now))
+            })
+            .sum()
+    }
+
+    pub fn forget(&mut self, key: &str) {
+        self.queries.retain(|_, selections| {
+            selections.remove(key);
+            !selections.is_empty()
+        });
+
+        self.save();
+    }
+
+    fn prune(&mut self, now: u64) {
+        let cutoff = now.saturating_sub(MAX_SELECTION_AGE_SECS);
+
+        self.queries.retain(|_, selections| {
+            selections.retain(|_, selection| selection.last_used >= cutoff);
+            !selections.is_empty()
+        });
+    }
+
+    fn save(&self) {
+        let file = KnowledgeFile {
+            version: KNOWLEDGE_VERSION,
+            queries: self.queries.clone(),
+        };
+
+        let result = serde_json::to_vec_pretty(&file)
+            .map_err(std::io::Error::from)
+            .and_then(|data| write_atomic(&self.path, &data));
+
+        if let Err(e) = result {
+            eprintln!("Failed to save knowledge: {}", e);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    const NOW: u64 = 1_700_000_000;
+    const DAY: u64 = 24 * 60 * 60;
+    const GIMP: &str = "/usr/share/applications/gimp.desktop";
+
+    fn knowledge(name: &str) -> Knowledge {
+        let path = std::env::temp_dir().join(format!("bsearch-knowledge-{}-{}.json", name, std::process::id()));
+        let _ = fs::remove_file(&path);
+        Knowledge::load(path)
+    }
+
+    fn assert_close(actual: f64, expected: f64, what: &str) {
+        assert!((actual - expected).abs() < 1e-9, "{}: got {}, expected {}", what, actual, expected);
+    }
+
+    #[test]
+    fn record_saves_normalized_selections() {
+        let mut knowledge = knowledge("record");
+        knowledge.record_at("  GIMP ", GIMP, NOW - DAY);
+        knowledge.record_at("gimp", GIMP, NOW);
+        knowledge.record_at("   ", GIMP, NOW);
+
+        let selection = knowledge.queries["gimp"][GIMP];
+        assert_eq!((selection.count, selection.last_used), (2, NOW));
+        assert_eq!(knowledge.queries.len(), 1, "blank queries are not learned");
+
+        let reloaded = Knowledge::load(knowledge.path.clone());
+        fs::remove_file(&knowledge.path).unwrap();
+        assert_eq!(reloaded.queries["gimp"][GIMP].count, 2, "selections survive a reload");
+    }
+
+    #[test]
+    fn boost_weighs_learned_prefixes_by_coverage() {
+        let mut knowledge = knowledge("boost");
+        knowledge.record_at("gi", GIMP, NOW);
+        for _ in 0..8 {
+            knowledge.record_at("gimp", GIMP, NOW);
+        }
+        fs::remove_file(&knowledge.path).unwrap();
+
+        let cases: &[(&str, f64)] = &[
+            // "gi" covers half the query once; "gimp" all of it, capped at five picks.
+            ("gimp", 0.5 + 5.0),
+            ("GIMP", 0.5 + 5.0),
+            ("gi", 1.0),
+            ("g", 0.0),
+            ("gimpy", 2.0 / 5.0 + 4.0 / 5.0 * 5.0),
+            ("", 0.0),
+        ];
+
+        for (query, expected) in cases {
+            assert_close(knowledge.boost_at(query, GIMP, NOW), *expected, &format!("query {:?}", query));
+        }
+        assert_close(knowledge.boost_at("gimp", "/usr/bin/other", NOW), 0.0, "unpicked result");
+    }
+
+    #[test]
+    fn selections_decay_with_a_thirty_day_half_life() {
+        let mut knowledge = knowledge("decay");
+        knowledge.record_at("gimp", GIMP, NOW);
+        fs::remove_file(&knowledge.path).unwrap();
+
+        assert_close(knowledge.boost_at("gimp", GIMP, NOW + 30 * DAY), 0.5, "one half-life");
+        assert_close(knowledge.boost_at("gimp", GIMP, NOW + 60 * DAY), 0.25, "two half-lives");
+
+        knowledge.prune(NOW + MAX_SELECTION_AGE_SECS + 1);
+        assert!(knowledge.queries.is_empty(), "selections past ten half-lives are pruned");
+    }
+
+    #[test]
+    fn migrates_known_versions_only() {
+        let current = json!({ "version": 1, "queries": { "gimp": { GIMP: { "count": 3, "last_used": NOW } } } });
+        assert_eq!(migrate(current).unwrap().queries["gimp"][GIMP].count, 3);
+
+        assert!(migrate(json!({ "version": 2, "queries": {} })).is_err());
+        assert!(migrate(json!({ "queries": {} })).is_err());
+        assert!(migrate(json!({ "version": 1, "queries": { "gimp": 3 } })).is_err());
+    }
+}
@@ -1,402 +1,189 @@
 use std::fs;
-use std::env;
-use std::fs::File;
 use std::sync::Arc;
-use std::ptr::null_mut;
+use std::sync::Mutex;
 use std::process::Command;
-use std::sync::{Once, Mutex};
-use std::collections::HashMap;
+use std::collections::HashSet;
 use std::path::{Path, PathBuf};
-use std::sync::atomic::AtomicBool;
-use std::sync::atomic::{AtomicU64, Ordering};
 
-use winreg::enums::*;
-use winreg::{HKEY, RegKey};
-
-use windows::core::{Interface, PCWSTR};
-use windows::Win32::Foundation::COLORREF;
-use windows::Win32::UI::Shell::{IShellLinkW, ShellLink};
-use windows::Win32::System::Com::{CoCreateInstance, CoInitialize, CLSCTX_INPROC_SERVER};
-
-use image::ImageOutputFormat;
 use fuzzy_matcher::FuzzyMatcher;
 use global_hotkey::{GlobalHotKeyManager, GlobalHotKeyEvent, hotkey::{Code, HotKey, Modifiers}};
 use tauri::{Manager, Runtime, State, Emitter, AppHandle};
-use walkdir::WalkDir;
 use serde::{Serialize, Deserialize};
 use base64::{engine::general_purpose, Engine as _};
 
-static ICON_ID: AtomicU64 = AtomicU64::new(1);
-static INIT: Once = Once::new();
-
-struct AppFrequencyTracker(Mutex<HashMap<String, u32>>);
-
-#[derive(Clone, Debug, serde::Serialize)]
-pub struct AppInfo {
-    name: String,
-    path: String,
-    icon_path: Option<String>,
-    is_shortcut: bool,
-}
-
-struct AppCache {
-    apps: Arc<Mutex<Vec<AppInfo>>>,
-    last_update: Arc<Mutex<std::time::Instant>>,
-    is_updating: Arc<AtomicBool>,
+pub mod provider;
+mod config;
+mod history;
+mod knowledge;
+mod app_index;
+mod watcher;
+mod icon_cache;
+mod ico;
+mod pe_icon;
+mod shell_link;
+mod registry;
+mod exe_select;
+mod app_merge;
+mod scan;
+mod normalize;
+mod ranking;
+mod search_index;
+mod typo;
+#[cfg(windows)]
+mod windows_apps;
+#[cfg(target_os = "linux")]
+mod desktop_entry;
+#[cfg(target_os = "linux")]
+mod desktop_exec;
+#[cfg(target_os = "linux")]
+mod icon_theme;
+#[cfg(target_os = "linux")]
+mod linux_apps;
+#[cfg(target_os = "linux")]
+mod wine_apps;
+#[cfg(target_os = "linux")]
+mod sandboxed_apps;
+#[cfg(target_os = "linux")]
+mod squashfs;
+#[cfg(target_os = "linux")]
+mod appimage_apps;
+
+use history::LaunchHistory;
+use knowledge::Knowledge;
+use provider::{AppProvider, SearchRegistry, WebSearchProvider};
+use search_index::{FormKind, NameMatch, SearchIndex};
+
+#[derive(Clone)]
+struct AppFrequencyTracker(Arc<Mutex<LaunchHistory>>);
+
+impl AppFrequencyTracker {
+    fn load() -> Self {
+        Self(Arc::new(Mutex::new(LaunchHistory::load(history::history_path()))))
+    }
 }
 
-#[derive(Serialize, Deserialize, Clone)]
-struct AppResult {
-    #[serde(rename = "type")]
-    result_type: String,
-    title: String,
-    path: String,
-    icon_path: Option<String>,
-}
+#[derive(Clone)]
+struct QueryKnowledge(Arc<Mutex<Knowledge>>);
 
-fn get_special_folders() -> Vec<PathBuf> {
-    let mut folders = Vec::new();
-    
-    if let Ok(userprofile) = env::var("USERPROFILE") {
-        folders.push(PathBuf::from(userprofile).join("Desktop"));
-    }
-    
-    if let Ok(public) = env::var("PUBLIC") {
-        folders.push(PathBuf::from(public).join("Desktop"));
-    }
-    
-    if let Ok(appdata) = env::var("APPDATA") {
-        folders.push(PathBuf::from(appdata).join("Microsoft\\Windows\\Start Menu\\Programs"));
-    }
-    
-    if let Ok(programdata) = env::var("ProgramData") {
-        folders.push(PathBuf::from(programdata).join("Microsoft\\Windows\\Start Menu\\Programs"));
+impl QueryKnowledge {
+    fn load() -> Self {
+        Self(Arc::new(Mutex::new(Knowledge::load(knowledge::knowledge_path()))))
     }
-    
-    folders
 }
 
-fn find_executables_in_dir(dir: &Path, app_name: &str, apps: &mut Vec<AppInfo>) {
-    for entry in WalkDir::new(dir)
-        .max_depth(2)
-        .into_iter()
-        .filter_map(|e| e.ok())
-        .filter(|e| e.path().extension().map_or(false, |ext| ext == "exe")) {
-        
-        apps.push(AppInfo {
-            name: app_name.to_string(),
-            path: entry.path().to_string_lossy().to_string(),
-            icon_path: None,
-            is_shortcut: false,
-        });
-        
-        break;
-    }
+// How an app is packaged, when that changes how it has to be launched.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "lowercase")]
+pub enum Packaging {
+    Flatpak { app_id: String },
+    Snap { name: String },
+    AppImage,
 }
 
-fn get_shortcuts_from_special_folders() -> Vec<AppInfo> {
-    let mut shortcuts = Vec::new();
-    let special_folders = get_special_folders();
-    
-    for folder in special_folders {
-        if !folder.exists() {
-            continue;
-        }
-        
-        for entry in WalkDir::new(folder)
-            .into_iter()
-            .filter_map(|e| e.ok())
-            .filter(|e| e.path().extension().map_or(false, |ext| ext.to_string_lossy().to_lowercase() == "lnk")) {
-            
-            let path = entry.path();
-            let shortcut_path = path.to_string_lossy().to_string();
-            
-            if let Some(target_path) = resolve_shortcut(&shortcut_path) {
-                let name = path.file_stem()
-                    .and_then(|s| s.to_str())
-                    .unwrap_or("Unknown")
-                    .to_string();
-                
-                shortcuts.push(AppInfo {
-                    name,
-                    path: target_path,
-                    icon_path: None,
-                    is_shortcut: true,
-                });
-            }
-        }
-    }
-    
-    shortcuts
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct AppInfo {
+    name: String,
+    path: String,
+    icon_path: Option<String>,
+    icon_path_2x: Option<String>,
+    is_shortcut: bool,
+    generic_name: Option<String>,
+    keywords: Vec<String>,
+    exec: Option<String>,
+    icon_name: Option<String>,
+    terminal: bool,
+    working_dir: Option<String>,
+    source: Option<String>,
+    arguments: Option<String>,
+    #[serde(default)]
+    icon_index: i32,
+    wine_prefix: Option<String>,
+    selection_rationale: Option<String>,
+    #[serde(default)]
+    origins: Vec<String>,
+    packaging: Option<Packaging>,
 }
 
-fn resolve_shortcut(shortcut_path: &str) -> Option<String> {
-    use windows::Win32::System::Com::{IPersistFile, STGM};
-    use std::ffi::OsString;
-    use std::os::windows::ffi::OsStringExt;
-
-    unsafe {
-        let _ = CoInitialize(None);
-
-        let shell_link: IShellLinkW = CoCreateInstance(
-            &ShellLink,
-            None,
-            CLSCTX_INPROC_SERVER
-        ).ok()?;
-
-        let persist_file: IPersistFile = shell_link.cast().ok()?;
-        
-        let wide_path: Vec<u16> = shortcut_path.encode_utf16().chain(std::iter::once(0)).collect();
-        
-        persist_file.Load(PCWSTR(wide_path.as_ptr()), STGM::default()).ok()?;
-
-        let mut buffer = [0u16; 260];
-        let mut fd = windows::Win32::Storage::FileSystem::WIN32_FIND_DATAW::default();
-        
-        shell_link.GetPath(
-            &mut buffer,
-            &mut fd,
-            0
-        ).ok()?;
-        
-        let len = buffer.iter().position(|&c| c == 0).unwrap_or(buffer.len());
-        let os_string = OsString::from_wide(&buffer[0..len]);
-        
-        os_string.into_string().ok()
+impl AppInfo {
+    fn search_terms(&self) -> impl Iterator<Item = &str> {
+        std::iter::once(self.name.as_str())
+            .chain(self.generic_name.as_deref())
+            .chain(self.keywords.iter().map(|k| k.as_str()))
     }
 }
 
-fn get_uninstall_apps(hkey: HKEY, apps: &mut Vec<AppInfo>) {
-    if let Ok(uninstall) = RegKey::predef(hkey)
-        .open_subkey("SOFTWARE\\Microsoft\\Windows\\CurrentVersion\\Uninstall") {
-        
-        for key_result in uninstall.enum_keys().flatten() {
-            if let Ok(app_key) = uninstall.open_subkey(&key_result) {
-                if let (Ok(name), Ok(location)) = (
-                    app_key.get_value::<String, _>("DisplayName"),
-                    app_key.get_value::<String, _>("InstallLocation")
-                ) {
-                    if !location.is_empty() {
-                        let location_path = PathBuf::from(&location);
-                        find_executables_in_dir(&location_path, &name, apps);
-                    }
-                }
-            }
-        }
-    }
+#[derive(Clone)]
+struct AppCache {
+    apps: Arc<Mutex<Vec<AppInfo>>>,
+    // Everything the backends found, before duplicates are merged into `apps`.
+    discovered: Arc<Mutex<Vec<AppInfo>>>,
+    // Rebuilt together with `apps`, which it mirrors entry for entry.
+    search_index: Arc<Mutex<Arc<SearchIndex>>>,
+    app_handle: Arc<Mutex<Option<AppHandle>>>,
 }
 
-fn get_installed_apps_from_registry() -> Vec<AppInfo> {
-    let mut apps = Vec::new();
-    
-    if let Ok(app_paths) = RegKey::predef(HKEY_LOCAL_MACHINE)
-        .open_subkey("SOFTWARE\\Microsoft\\Windows\\CurrentVersion\\App Paths") {
-        
-        for key_result in app_paths.enum_keys().flatten() {
-            if let Ok(app_key) = app_paths.open_subkey(&key_result) {
-                if let Ok(path) = app_key.get_value::<String, _>("") {
-                    let name = Path::new(&key_result)
-                        .file_stem()
-                        .and_then(|s| s.to_str())
-                        .unwrap_or(&key_result)
-                        .to_string();
-                    
-                    apps.push(AppInfo {
-                        name,
-                        path,
-                        icon_path: None,
-                        is_shortcut: false,
-                    });
-                }
-            }
-        }
-    }
-    
-    get_uninstall_apps(HKEY_LOCAL_MACHINE, &mut apps);
-    
-    get_uninstall_apps(HKEY_CURRENT_USER, &mut apps);
-    
-    apps
+#[derive(Serialize, Deserialize, Clone)]
+pub struct AppResult {
+    #[serde(rename = "type")]
+    pub result_type: String,
+    pub title: String,
+    pub path: String,
+    pub icon_path: Option<String>,
+    #[serde(default)]
+    pub icon_path_2x: Option<String>,
+    /// Which spelling of the app's name matched the query, for app results.
+    #[serde(default)]
+    pub match_form: Option<FormKind>,
+    #[serde(default)]
+    pub title_highlights: Vec<HighlightRange>,
+    #[serde(default)]
+    pub path_highlights: Vec<HighlightRange>,
+    /// Set when the result only matched after correcting typos in the query.
+    #[serde(default)]
+    pub did_you_mean: bool,
 }
 
-fn init_icon_cache() -> PathBuf {
-    let cache_dir = dirs::cache_dir()
-        .unwrap_or_else(|| PathBuf::from("."))
-        .join("bsearch")
-        .join("icons");
-
-    if !cache_dir.exists() {
-        std::fs::create_dir_all(&cache_dir).unwrap_or_else(|_| {
-            eprintln!("Failed to create icon cache directory");
-        });
-    }
-
-    cache_dir
+/// A matched span of a result's text, in UTF-16 offsets so the frontend can
+/// slice JavaScript strings with it directly.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct HighlightRange {
+    pub start: usize,
+    pub end: usize,
 }
 
-fn extract_icon_from_exe(exe_path: &str) -> Option<String> {
-    INIT.call_once(|| {
-        let _ = init_icon_cache();
-    });
-
-    let icon_id = ICON_ID.fetch_add(1, Ordering::SeqCst);
-    let cache_dir = dirs::cache_dir()
-        .unwrap_or_else(|| PathBuf::from("."))
-        .join("bsearch")
-        .join("icons");
-    
-    let icon_path = cache_dir.join(format!("icon_{}.png", icon_id));
-    
-    unsafe {
-        let _ = CoInitialize(None);
-        
-        let wide_path: Vec<u16> = exe_path.encode_utf16().chain(std::iter::once(0)).collect();
-        
-        let hicon = windows::Win32::UI::Shell::ExtractIconW(
-            Some(windows::Win32::Foundation::HINSTANCE(null_mut())),
-            PCWSTR(wide_path.as_ptr()),
-            0
-        );
-        
-        if !hicon.is_invalid() {
-            let hdc = windows::Win32::Graphics::Gdi::GetDC(None);
-            if hdc.is_invalid() {
-                let _ = windows::Win32::UI::WindowsAndMessaging::DestroyIcon(hicon);
-                return None;
-            }
-            
-            let hdc_mem = windows::Win32::Graphics::Gdi::CreateCompatibleDC(Some(hdc));
-            if hdc_mem.is_invalid() {
-                let _ = windows::Win32::Graphics::Gdi::ReleaseDC(None, hdc);
-                let _ = windows::Win32::UI::WindowsAndMessaging::DestroyIcon(hicon);
-                return None;
-            }
-            
-            let hbmp = windows::Win32::Graphics::Gdi::CreateCompatibleBitmap(hdc, 48, 48);
-            if hbmp.is_invalid() {
-                let _ = windows::Win32::Graphics::Gdi::DeleteDC(hdc_mem);
-                let _ = windows::Win32::Graphics::Gdi::ReleaseDC(None, hdc);
-                let _ = windows::Win32::UI::WindowsAndMessaging::DestroyIcon(hicon);
-                return None;
-            }
-            
-            let old_obj = windows::Win32::Graphics::Gdi::SelectObject(hdc_mem, hbmp.into());
-            
-            let _ = windows::Win32::Graphics::Gdi::SetBkColor(hdc_mem, COLORREF(0x00FFFFFF));
-            let _ = windows::Win32::Graphics::Gdi::SetBkMode(hdc_mem, windows::Win32::Graphics::Gdi::TRANSPARENT);
-            
-            let _ = windows::Win32::UI::WindowsAndMessaging::DrawIconEx(
-                hdc_mem,
-                0, 0,
-                hicon,
-                48, 48,
-                0,
-                None,
-                windows::Win32::UI::WindowsAndMessaging::DI_NORMAL
-            );
-            
-            let result = save_bitmap_as_png(hbmp, &icon_path);
-            
-            let _ = windows::Win32::Graphics::Gdi::SelectObject(hdc_mem, old_obj);
-            let _ = windows::Win32::Graphics::Gdi::DeleteObject(hbmp.into());
-            let _ = windows::Win32::Graphics::Gdi::DeleteDC(hdc_mem);
-            let _ = windows::Win32::Graphics::Gdi::ReleaseDC(None, hdc);
-            let _ = windows::Win32::UI::WindowsAndMessaging::DestroyIcon(hicon);
-            
-            if result {
-                return Some(icon_path.to_string_lossy().to_string());
+// Turns matched char indices of `text` into UTF-16 ranges, merging runs of
+// adjacent chars.
+pub(crate) fn highlight_ranges(text: &str, positions: &[usize]) -> Vec<HighlightRange> {
+    let mut ranges: Vec<HighlightRange> = Vec::new();
+    let mut offset = 0;
+    
+    for (i, c) in text.chars().enumerate() {
+        let len = c.len_utf16();
+        if positions.contains(&i) {
+            match ranges.last_mut() {
+                Some(range) if range.end == offset => range.end += len,
+                _ => ranges.push(HighlightRange { start: offset, end: offset + len }),
             }
         }
+        offset += len;
     }
     
-    let default_icon_path = cache_dir.join("default_icon.png");
-    
-    if !default_icon_path.exists() {
-        create_default_icon(&default_icon_path);
-    }
-    
-    if default_icon_path.exists() {
-        Some(default_icon_path.to_string_lossy().to_string())
-    } else {
-        None
-    }
+    ranges
 }
 
-fn save_bitmap_as_png(hbmp: windows::Win32::Graphics::Gdi::HBITMAP, path: &Path) -> bool {
-    unsafe {
-        let mut bmi: windows::Win32::Graphics::Gdi::BITMAPINFO = std::mem::zeroed();
-        bmi.bmiHeader.biSize = std::mem::size_of::<windows::Win32::Graphics::Gdi::BITMAPINFOHEADER>() as u32;
-        bmi.bmiHeader.biWidth = 48;
-        bmi.bmiHeader.biHeight = -48;
-        bmi.bmiHeader.biPlanes = 1;
-        bmi.bmiHeader.biBitCount = 32;
-        bmi.bmiHeader.biCompression = windows::Win32::Graphics::Gdi::BI_RGB.0;
-        
-        let data_size = 48 * 48 * 4;
-        let mut buffer = vec![0u8; data_size];
-        
-        let dc = windows::Win32::Graphics::Gdi::GetDC(None);
-        let result = windows::Win32::Graphics::Gdi::GetDIBits(
-            dc,
-            hbmp,
-            0,
-            48,
-            Some(buffer.as_mut_ptr() as *mut std::ffi::c_void),
-            &mut bmi,
-            windows::Win32::Graphics::Gdi::DIB_RGB_COLORS
-        );
-        let _ = windows::Win32::Graphics::Gdi::ReleaseDC(None, dc);
-        
-        if result == 0 {
-            return false;
-        }
-        
-        let file = match File::create(path) {
-            Ok(f) => f,
-            Err(_) => return false,
-        };
-        let writer = std::io::BufWriter::new(file);
-        
-        use image::{ImageBuffer, Rgba};
-        
-        let mut img = ImageBuffer::<Rgba<u8>, _>::new(48, 48);
-        
-        for y in 0..48 {
-            for x in 0..48 {
-                let i = (y * 48 + x) * 4;
-                let b = buffer[i];
-                let g = buffer[i + 1];
-                let r = buffer[i + 2];
-                let a = buffer[i + 3];
-                
-                img.put_pixel(x as u32, y as u32, Rgba([r, g, b, a]));
-            }
-        }
-        
-        img.write_to(&mut writer.into_inner().unwrap(), ImageOutputFormat::Png).is_ok()
-    }
-}
-
-fn create_default_icon(path: &Path) -> bool {
-    use image::{ImageBuffer, Rgba};
-    
-    let mut img = ImageBuffer::<Rgba<u8>, _>::new(48, 48);
-    
-    for y in 0..48 {
-        for x in 0..48 {
-            let dx = x as i32 - 24;
-            let dy = y as i32 - 24;
-            let color = if dx * dx + dy * dy < 20 * 20 {
-                Rgba([100, 149, 237, 255])
-            } else {
-                Rgba([0, 0, 0, 0])
-            };
+// Highlights the (folded) query in the file name part of an app's path.
+fn path_highlights(path: &str, matcher: &impl FuzzyMatcher, query: &str) -> Vec<HighlightRange> {
+    let file_name_start = path.rfind(['/', '\\']).map_or(0, |i| i + 1);
+    let skipped = path[..file_name_start].chars().count();
+    let (file_name, origins) = normalize::fold_indexed(&path[file_name_start..]);
     
-            img.put_pixel(x, y, color);
+    match matcher.fuzzy_indices(&file_name, query) {
+        Some((_, positions)) => {
+            let positions: Vec<usize> = positions.iter().map(|&i| origins[i] + skipped).collect();
+            highlight_ranges(path, &positions)
         }
+        None => Vec::new(),
     }
-    
-    img.save(path).is_ok()
 }
 
 #[tauri::command]
@@ -413,94 +200,103 @@ async fn get_icon_data(path: String) -> Result<String, String> {
     }
 }
 
-pub fn search_windows_apps(query: &str) -> Vec<AppInfo> {
-    let mut all_apps = Vec::new();
-    
-    let mut shortcuts = get_shortcuts_from_special_folders();
-    
-    for app in &mut shortcuts {
-        if app.icon_path.is_none() {
-            app.icon_path = extract_icon_from_exe(&app.path);
-        }
-    }
-    all_apps.extend(shortcuts);
-    
-    let mut registry_apps = get_installed_apps_from_registry();
-    
-    for app in &mut registry_apps {
-        if app.icon_path.is_none() {
-            app.icon_path = extract_icon_from_exe(&app.path);
-        }
-    }
-    all_apps.extend(registry_apps);
-    
-    if query.is_empty() {
-        return all_apps;
-    }
-    
-    let matcher = fuzzy_matcher::skim::SkimMatcherV2::default();
-    let mut matched_apps: Vec<(i64, AppInfo)> = Vec::new();
-    
-    for app in all_apps {
-        if let Some(score) = matcher.fuzzy_match(&app.name.to_lowercase(), &query.to_lowercase()) {
-            matched_apps.push((score, app));
-        }
-    }
-    
-    matched_apps.sort_by(|a, b| b.0.cmp(&a.0));
-    
-    matched_apps.into_iter()
-        .map(|(_, app)| app)
+#[tauri::command]
+fn search_apps(query: &str, registry: State<'_, SearchRegistry>) -> Vec<AppResult> {
+    registry.search(query)
+        .into_iter()
+        .filter(|result| result.result_type == "app")
         .collect()
 }
 
 #[tauri::command]
-fn search_apps(query: &str, app_cache: State<'_, AppCache>, app_tracker: State<'_, AppFrequencyTracker>) -> Vec<AppResult> {
-    app_cache.update_if_needed();
-    
-    let all_apps = app_cache.get_apps();
+fn search(query: &str, registry: State<'_, SearchRegistry>) -> Vec<AppResult> {
+    registry.search(query)
+}
+
+#[tauri::command]
+fn activate_result(result: AppResult, query: Option<String>, registry: State<'_, SearchRegistry>) -> Result<(), String> {
+    registry.activate(&result, query.as_deref().unwrap_or(""))
+}
+
+#[tauri::command]
+fn forget_selection(path: &str, knowledge: State<'_, QueryKnowledge>) {
+    knowledge.0.lock().unwrap().forget(path);
+}
+
+fn search_app_results(query: &str, app_cache: &AppCache, app_tracker: &AppFrequencyTracker) -> Vec<(i64, AppResult)> {
+    let (all_apps, search_index) = app_cache.snapshot();
     
     if query.is_empty() {
-        return convert_to_results(&all_apps, &app_tracker);
+        return score_apps(all_apps.iter().map(|app| (app, None)), app_tracker);
     }
     
     let matcher = fuzzy_matcher::skim::SkimMatcherV2::default();
-    let mut matched_apps: Vec<(i64, &AppInfo)> = Vec::new();
+    let query = normalize::fold(query);
+    let mut matched_apps: Vec<(&AppInfo, NameMatch)> = Vec::new();
     
-    for app in &all_apps {
-        if let Some(score) = matcher.fuzzy_match(&app.name.to_lowercase(), &query.to_lowercase()) {
-            matched_apps.push((score, app));
+    for (i, app) in all_apps.iter().enumerate() {
+        if let Some(name_match) = search_index.find(i, &matcher, &query) {
+            matched_apps.push((app, name_match));
         }
     }
     
-    matched_apps.sort_by(|a, b| b.0.cmp(&a.0));
+    matched_apps.sort_by_key(|(_, name_match)| std::cmp::Reverse(name_match.score));
+    
+    if matched_apps.is_empty() {
+        let corrections = search_index.corrections(&query);
+        return score_apps(corrections.iter().map(|(i, name_match)| (&all_apps[*i], Some(name_match))), app_tracker);
+    }
+    
+    let mut results = score_apps(matched_apps.iter().map(|(app, name_match)| (*app, Some(name_match))), app_tracker);
+    for (_, result) in results.iter_mut() {
+        result.path_highlights = path_highlights(&result.path, &matcher, &query);
+    }
     
-    convert_to_results(&matched_apps.into_iter().map(|(_, app)| app.clone()).collect::<Vec<_>>(), &app_tracker)
+    results
 }
 
 #[tauri::command]
-fn get_frequent_apps(app_tracker: State<'_, AppFrequencyTracker>) -> Vec<AppResult> {
-    let tracker = app_tracker.0.lock().unwrap();
-    
-    let mut apps: Vec<(String, u32)> = tracker.clone().into_iter().collect();
-    apps.sort_by(|a, b| b.1.cmp(&a.1));
+fn get_frequent_apps(app_cache: State<'_, AppCache>, app_tracker: State<'_, AppFrequencyTracker>) -> Vec<AppResult> {
+    let all_apps = app_cache.get_apps();
+    let apps = app_tracker.0.lock().unwrap().top(6);
     
     apps.into_iter()
-        .take(6)
         .map(|(path, _)| {
+            if let Some(app) = all_apps.iter().find(|app| app.path == path) {
+                return AppResult {
+                    result_type: "app".to_string(),
+                    title: app.name.clone(),
+                    path,
+                    icon_path: app.icon_path.clone(),
+                    icon_path_2x: app.icon_path_2x.clone(),
+                    match_form: None,
+                    title_highlights: Vec::new(),
+                    path_highlights: Vec::new(),
+                    did_you_mean: false,
+                };
+            }
+            
             let file_name = PathBuf::from(&path)
                 .file_stem()
                 .and_then(|s| s.to_str())
                 .unwrap_or("Unknown App")
                 .to_string();
             
-            let icon_path = extract_icon_from_exe(&path);
+            #[cfg(windows)]
+            let icon_path = windows_apps::extract_icon_from_exe(&path);
+            #[cfg(not(windows))]
+            let icon_path = None;
             
             AppResult {
                 result_type: "app".to_string(),
                 title: file_name,
                 path,
                 icon_path,
+                icon_path_2x: None,
+                match_form: None,
+                title_highlights: Vec::new(),
+                path_highlights: Vec::new(),
+                did_you_mean: false,
             }
         })
         .collect()
@@ -508,139 +304,344 @@ fn get_frequent_apps(app_tracker: State<'_, AppFrequencyTracker>) -> Vec<AppResu
 
 impl AppCache {
     fn new() -> Self {
-        let cache = Self {
-            apps: Arc::new(Mutex::new(Vec::new())),
-            last_update: Arc::new(Mutex::new(std::time::Instant::now() - std::time::Duration::from_secs(600))),
-            is_updating: Arc::new(AtomicBool::new(false)),
-        };
-        
-        cache.update_if_needed();
+        let indexed_apps = app_index::load_index(&app_index::index_path());
+        let search_index = SearchIndex::build(&indexed_apps);
         
-        cache
+        Self {
+            apps: Arc::new(Mutex::new(indexed_apps)),
+            discovered: Arc::new(Mutex::new(Vec::new())),
+            search_index: Arc::new(Mutex::new(Arc::new(search_index))),
+            app_handle: Arc::new(Mutex::new(None)),
+        }
+    }
+    
+    fn attach(&self, app_handle: AppHandle) {
+        *self.app_handle.lock().unwrap() = Some(app_handle);
     }
     
     fn get_apps(&self) -> Vec<AppInfo> {
-        let apps = self.apps.lock().unwrap().clone();
+        self.apps.lock().unwrap().clone()
+    }
+    
+    // The apps together with the search index built from exactly those apps.
+    fn snapshot(&self) -> (Vec<AppInfo>, Arc<SearchIndex>) {
+        let apps = self.apps.lock().unwrap();
+        (apps.clone(), self.search_index.lock().unwrap().clone())
+    }
+    
+    fn replace_apps(&self, apps: Vec<AppInfo>) -> Vec<AppInfo> {
+        let search_index = Arc::new(SearchIndex::build(&apps));
         
-        if apps.is_empty() && !self.is_updating.load(Ordering::SeqCst) {
-            let fresh_apps = search_windows_apps("");
-            let mut cache_apps = self.apps.lock().unwrap();
-            *cache_apps = fresh_apps;
-            return cache_apps.clone();
-        }
+        let mut cache_apps = self.apps.lock().unwrap();
+        *self.search_index.lock().unwrap() = search_index;
+        std::mem::replace(&mut *cache_apps, apps)
+    }
+    
+    fn find(&self, path: &str) -> Option<AppInfo> {
+        self.apps.lock().unwrap()
+            .iter()
+            .find(|app| app.path == path)
+            .cloned()
+    }
+    
+    fn rescan_all(&self) {
+        let discovered = collect_all_apps();
+        let apps = app_merge::merge_apps(&discovered);
+        
+        let old_apps = self.replace_apps(apps.clone());
         
-        apps
+        self.publish(&old_apps, &apps);
+        
+        let referenced: HashSet<String> = discovered.iter()
+            .flat_map(|app| app.icon_path.iter().chain(&app.icon_path_2x).cloned())
+            .collect();
+        *self.discovered.lock().unwrap() = discovered;
+        icon_cache::global().collect_garbage(&referenced, icon_cache::max_cache_bytes());
+        icon_cache::global().flush();
     }
     
-    fn update_if_needed(&self) {
-        let now = std::time::Instant::now();
-        let update_needed = {
-            let last = self.last_update.lock().unwrap();
-            now.duration_since(*last).as_secs() > 300
-        };
+    fn rescan_paths(&self, paths: &[PathBuf]) {
+        let mut files = Vec::new();
+        for path in paths {
+            if path.is_dir() {
+                #[cfg(target_os = "linux")]
+                let follow_symlinks = config::get().scan.follow_symlinks || linux_apps::in_application_dir(path);
+                #[cfg(not(target_os = "linux"))]
+                let follow_symlinks = config::get().scan.follow_symlinks;
+
+                files.extend(scan::walk_with(path, None, follow_symlinks)
+                    .filter(|e| e.file_type().is_file())
+                    .map(|e| e.into_path()));
+            } else {
+                files.push(path.clone());
+            }
+        }
         
-        if update_needed && !self.is_updating.load(Ordering::SeqCst) {
-            self.is_updating.store(true, Ordering::SeqCst);
-            
-            let apps_clone = Arc::clone(&self.apps);
-            let last_update_clone = Arc::clone(&self.last_update);
-            let is_updating_clone = Arc::clone(&self.is_updating);
-            
-            std::thread::spawn(move || {                
-                let apps = collect_all_apps();
-                
-                {
-                    let mut cache_apps = apps_clone.lock().unwrap();
-                    *cache_apps = apps;
-                    
-                    let mut last_update = last_update_clone.lock().unwrap();
-                    *last_update = std::time::Instant::now();
+        let batch = RescanBatch::new();
+        let mut stale_sources = HashSet::new();
+        let mut removed_dirs = Vec::new();
+        let mut fresh_apps = Vec::new();
+        
+        for file in &files {
+            match rescan_file(file, &batch) {
+                Some((sources, apps)) => {
+                    stale_sources.extend(sources);
+                    fresh_apps.extend(apps);
                 }
-                
-                is_updating_clone.store(false, Ordering::SeqCst);
+                // Something we don't index directly vanished; it may have been
+                // a folder, so drop everything that was discovered beneath it.
+                None if !file.exists() => removed_dirs.push(file.clone()),
+                None => {}
+            }
+        }
+        
+        let (old_apps, new_apps) = {
+            let mut discovered = self.discovered.lock().unwrap();
+            discovered.retain(|app| match &app.source {
+                Some(source) => !stale_sources.contains(source)
+                    && !removed_dirs.iter().any(|dir| Path::new(source).starts_with(dir)),
+                None => true,
             });
+            discovered.extend(fresh_apps);
+            
+            let new_apps = app_merge::merge_apps(&discovered);
+            let old_apps = self.replace_apps(new_apps.clone());
+            
+            (old_apps, new_apps)
+        };
+        
+        self.publish(&old_apps, &new_apps);
+        icon_cache::global().flush();
+    }
+    
+    fn publish(&self, old_apps: &[AppInfo], new_apps: &[AppInfo]) {
+        let diff = app_index::diff_apps(old_apps, new_apps);
+        if diff.is_empty() {
+            return;
+        }
+        
+        if let Err(e) = app_index::save_index(&app_index::index_path(), new_apps) {
+            eprintln!("Failed to save app index: {}", e);
+        }
+        
+        if let Some(app_handle) = self.app_handle.lock().unwrap().as_ref() {
+            if let Err(e) = app_handle.emit("apps-updated", &diff) {
+                eprintln!("Failed to emit apps-updated event: {}", e);
+            }
         }
     }
 }
 
-fn convert_to_results(apps: &[AppInfo], app_tracker: &State<'_, AppFrequencyTracker>) -> Vec<AppResult> {
+const SHORTCUT_BONUS: i64 = 100;
+const OTHER_BONUS: i64 = 50;
+const FREQUENCY_WEIGHT: f64 = 10.0;
+const MAX_FREQUENCY_BONUS: i64 = 10_000;
+
+// Match tiers have to outweigh every bonus combined.
+const _: () = assert!(
+    SHORTCUT_BONUS + ranking::MAX_FUZZY_BONUS + MAX_FREQUENCY_BONUS + provider::MAX_KNOWLEDGE_BONUS < ranking::TIER_STEP
+);
+
+fn score_apps<'a>(apps: impl IntoIterator<Item = (&'a AppInfo, Option<&'a NameMatch>)>, app_tracker: &AppFrequencyTracker) -> Vec<(i64, AppResult)> {
     let mut results: Vec<(i64, AppResult)> = Vec::new();
     
-    for app in apps {
-        let frequency = {
-            let tracker_guard = app_tracker.0.lock().unwrap();
-            *tracker_guard.get(&app.path).unwrap_or(&0)
-        };
+    for (app, name_match) in apps {
+        let frecency = app_tracker.0.lock().unwrap().frecency(&app.path);
         
-        const FREQUENCY_WEIGHT: i64 = 10;
-        let score = if app.is_shortcut { 100 } else { 50 };
-        let combined_score = score + (frequency as i64 * FREQUENCY_WEIGHT);
+        let score = if app.is_shortcut { SHORTCUT_BONUS } else { OTHER_BONUS };
+        let match_score = name_match.map_or(0, |name_match| name_match.score);
+        let frequency_bonus = ((frecency * FREQUENCY_WEIGHT) as i64).min(MAX_FREQUENCY_BONUS);
+        let combined_score = score + match_score + frequency_bonus;
         
         results.push((combined_score, AppResult {
             result_type: "app".to_string(),
             title: app.name.clone(),
             path: app.path.clone(),
             icon_path: app.icon_path.clone(),
+            icon_path_2x: app.icon_path_2x.clone(),
+            match_form: name_match.map(|name_match| name_match.form),
+            title_highlights: name_match
+                .filter(|name_match| name_match.term == 0)
+                .map(|name_match| highlight_ranges(&app.name, &name_match.positions))
+                .unwrap_or_default(),
+            path_highlights: Vec::new(),
+            did_you_mean: name_match.is_some_and(|name_match| name_match.corrected),
         }));
     }
     
     results.sort_by(|a, b| b.0.cmp(&a.0));
     
-    results.into_iter()
-        .map(|(_, app)| app)
-        .take(10)
-        .collect()
+    results
 }
 
 fn collect_all_apps() -> Vec<AppInfo> {
-    use rayon::prelude::*;
-    
     let mut all_apps = Vec::new();
     
-    let shortcuts = get_shortcuts_from_special_folders();
-    all_apps.extend(shortcuts);
+    #[cfg(windows)]
+    all_apps.extend(windows_apps::collect_apps());
     
-    let registry_apps = get_installed_apps_from_registry();
-    all_apps.extend(registry_apps);
+    #[cfg(target_os = "linux")]
+    all_apps.extend(linux_apps::collect_apps());
     
-    all_apps.par_iter_mut().for_each(|app| {
-        if app.icon_path.is_none() {
-            app.icon_path = extract_icon_from_exe(&app.path);
-        }
-    });
+    #[cfg(target_os = "linux")]
+    all_apps.extend(wine_apps::collect_apps());
+    
+    #[cfg(target_os = "linux")]
+    all_apps.extend(appimage_apps::collect_apps());
+    
+    all_apps.extend(collect_root_apps(scan::root_files()));
     
     all_apps
 }
 
-#[tauri::command]
-fn launch_app(app_path: &str, app_tracker: State<'_, AppFrequencyTracker>) -> Result<(), String> {
-    {
-        let mut tracker = app_tracker.0.lock().unwrap();
-        *tracker.entry(app_path.to_string()).or_insert(0) += 1;
+// Hands each file from the extra scan roots to the backend that understands
+// it; whatever is left is launched as is.
+fn collect_root_apps(mut files: Vec<PathBuf>) -> Vec<AppInfo> {
+    let mut apps = Vec::new();
+    
+    #[cfg(windows)]
+    apps.extend(windows_apps::root_apps(&mut files));
+    
+    #[cfg(target_os = "linux")]
+    apps.extend(linux_apps::root_apps(&mut files));
+    
+    #[cfg(target_os = "linux")]
+    apps.extend(appimage_apps::root_apps(&mut files));
+    
+    apps.extend(scan::file_apps(&files));
+    
+    apps
+}
+
+fn watch_roots() -> Vec<PathBuf> {
+    let mut roots = Vec::new();
+    
+    #[cfg(windows)]
+    roots.extend(windows_apps::watch_roots());
+    
+    #[cfg(target_os = "linux")]
+    roots.extend(linux_apps::watch_roots());
+    
+    #[cfg(target_os = "linux")]
+    roots.extend(wine_apps::watch_roots());
+    
+    #[cfg(target_os = "linux")]
+    roots.extend(appimage_apps::watch_roots());
+    
+    roots.extend(scan::root_paths());
+    
+    roots
+}
+
+// What the files of one watcher batch share, so it is computed once per
+// batch rather than once per changed file.
+struct RescanBatch {
+    roots: Vec<PathBuf>,
+    #[cfg(target_os = "linux")]
+    desktop_context: std::cell::OnceCell<linux_apps::ScanContext>,
+    #[cfg(target_os = "linux")]
+    wine_prefixes: std::cell::OnceCell<Vec<PathBuf>>,
+    #[cfg(target_os = "linux")]
+    appimage_context: std::cell::OnceCell<appimage_apps::RescanContext>,
+}
+
+impl RescanBatch {
+    fn new() -> Self {
+        Self {
+            roots: watch_roots(),
+            #[cfg(target_os = "linux")]
+            desktop_context: std::cell::OnceCell::new(),
+            #[cfg(target_os = "linux")]
+            wine_prefixes: std::cell::OnceCell::new(),
+            #[cfg(target_os = "linux")]
+            appimage_context: std::cell::OnceCell::new(),
+        }
+    }
+}
+
+// Returns the sources a changed file may have contributed and the apps it now
+// yields, or None when no discovery backend indexes that kind of file.
+fn rescan_file(path: &Path, batch: &RescanBatch) -> Option<(Vec<String>, Vec<AppInfo>)> {
+    // Full scans never reach excluded files.
+    if batch.roots.iter().any(|root| scan::is_excluded_within(root, path)) {
+        return None;
+    }
+    
+    #[cfg(windows)]
+    if let Some(rescanned) = windows_apps::rescan_file(path) {
+        return Some(rescanned);
+    }
+    
+    #[cfg(target_os = "linux")]
+    if let Some(rescanned) = linux_apps::rescan_file(path, &batch.desktop_context) {
+        return Some(rescanned);
+    }
+    
+    #[cfg(target_os = "linux")]
+    if let Some(rescanned) = wine_apps::rescan_file(path, &batch.wine_prefixes) {
+        return Some(rescanned);
     }
     
-    match Command::new(app_path).spawn() {
-        Ok(_) => Ok(()),
-        Err(e) => Err(format!("Open App Failed: {}", e))
+    #[cfg(target_os = "linux")]
+    if let Some(rescanned) = appimage_apps::rescan_file(path, &batch.appimage_context) {
+        return Some(rescanned);
     }
+    
+    if scan::is_root_file(path) {
+        let apps = collect_root_apps(vec![path.to_path_buf()]);
+        return Some((vec![path.to_string_lossy().to_string()], apps));
+    }
+    
+    None
 }
 
 #[tauri::command]
-async fn open_url(url: &str) -> Result<(), String> {
-    match tauri_plugin_opener::open_url(url, Option::<&str>::None) {
-        Ok(_) => Ok(()),
-        Err(e) => Err(format!("Open URL Failed: {}", e))
+fn launch_app(app_path: &str, query: Option<String>, app_cache: State<'_, AppCache>, app_tracker: State<'_, AppFrequencyTracker>, knowledge: State<'_, QueryKnowledge>) -> Result<(), String> {
+    launch_app_path(app_path, &app_cache, &app_tracker)?;
+    
+    if let Some(query) = query {
+        knowledge.0.lock().unwrap().record(&query, app_path);
+    }
+    
+    Ok(())
+}
+
+fn launch_app_path(app_path: &str, app_cache: &AppCache, app_tracker: &AppFrequencyTracker) -> Result<(), String> {
+    app_tracker.0.lock().unwrap().record_launch(app_path);
+    
+    match app_cache.find(app_path) {
+        #[cfg(target_os = "linux")]
+        Some(app) if app.wine_prefix.is_some() => wine_apps::launch_app(&app),
+        #[cfg(target_os = "linux")]
+        Some(app) if app.packaging.is_some() => sandboxed_apps::launch_app(&app),
+        #[cfg(target_os = "linux")]
+        Some(app) if app.exec.is_some() => desktop_exec::launch_desktop_app(&app, &[]),
+        #[cfg(windows)]
+        Some(app) => windows_apps::launch_app(&app),
+        _ => match Command::new(app_path).spawn() {
+            Ok(_) => Ok(()),
+            Err(e) => Err(format!("Open App Failed: {}", e))
+        }
     }
 }
 
 #[tauri::command]
-fn search_web(query: &str) -> Result<(), String> {
-    let search_url = format!("https://www.google.com/search?q={}", query);
+async fn open_url(url: &str, query: Option<String>, knowledge: State<'_, QueryKnowledge>) -> Result<(), String> {
+    if let Err(e) = tauri_plugin_opener::open_url(url, Option::<&str>::None) {
+        return Err(format!("Open URL Failed: {}", e));
+    }
     
-    match open::that(&search_url) {
-        Ok(_) => Ok(()),
-        Err(e) => Err(format!("Failed to perform a web search: {}", e))
+    if let Some(query) = query {
+        knowledge.0.lock().unwrap().record(&query, url);
     }
+    
+    Ok(())
+}
+
+#[tauri::command]
+fn search_web(query: &str, registry: State<'_, SearchRegistry>) -> Result<(), String> {
+    registry.activate_best("web", query.trim())
+        .map_err(|e| format!("Failed to perform a web search: {}", e))
 }
 
 fn setup_global_hotkeys<R: Runtime>(app: &tauri::App<R>) -> Result<(), Box<dyn std::error::Error>> {
@@ -694,8 +695,19 @@ async fn hide_main_window(window: tauri::Window) -> Result<(), String> {
 
 #[cfg_attr(mobile, tauri::mobile_entry_point)]
 pub fn run() {
+    let app_cache = AppCache::new();
+    let app_tracker = AppFrequencyTracker::load();
+    let knowledge = QueryKnowledge::load();
+    
+    let registry = SearchRegistry::new()
+        .register(AppProvider::new(app_cache.clone(), app_tracker.clone()))
+        .register(WebSearchProvider::default())
+        .with_knowledge(knowledge.clone());
+    
     tauri::Builder::default()
         .setup(|app| {
+            let _ = config::get();
+            
             if let Err(e) = setup_global_hotkeys(app) {
                 eprintln!("Failed to set global hotkey: {}", e);
             }
@@ -703,13 +715,22 @@ pub fn run() {
             let main_window = app.get_webview_window("main").unwrap();
             main_window.set_title("BSearch").unwrap();
 
+            let app_cache = app.state::<AppCache>();
+            app_cache.attach(app.handle().clone());
+            watcher::start(app_cache.inner().clone());
+
             Ok(())
         })
-        .manage(AppFrequencyTracker(Mutex::new(HashMap::new())))
-        .manage(AppCache::new())
+        .manage(app_tracker)
+        .manage(knowledge)
+        .manage(app_cache)
+        .manage(registry)
         .plugin(tauri_plugin_opener::init())
         .invoke_handler(tauri::generate_handler![
             search_apps,
+            search,
+            activate_result,
+            forget_selection,
             get_frequent_apps,
             get_icon_data,
             launch_app,
@@ -719,4 +740,50 @@ pub fn run() {
         ])
         .run(tauri::generate_context!())
         .expect("Runing Tauri App Error!!");
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn ranges(text: &str, positions: &[usize]) -> Vec<(usize, usize)> {
+        highlight_ranges(text, positions)
+            .into_iter()
+            .map(|range| (range.start, range.end))
+            .collect()
+    }
+
+    #[test]
+    fn highlights_in_utf16_offsets() {
+        let cases: &[(&str, &[usize], &[(usize, usize)])] = &[
+            ("Firefox", &[0, 1, 2], &[(0, 3)]),
+            ("Firefox", &[0, 4], &[(0, 1), (4, 5)]),
+            // Astral-plane chars take two UTF-16 units.
+            ("😀Chat", &[1, 2], &[(2, 4)]),
+            ("a😀b", &[1], &[(1, 3)]),
+            ("a😀b", &[1, 2], &[(1, 4)]),
+            ("😀😀x", &[0, 1], &[(0, 4)]),
+            ("🎵网易云音乐", &[1, 2, 3], &[(2, 5)]),
+            // CJK in the BMP is one unit per char.
+            ("微信 WeChat", &[0, 1], &[(0, 2)]),
+            ("微信 WeChat", &[3, 4], &[(3, 5)]),
+            ("网易云音乐", &[0, 2, 3], &[(0, 1), (2, 4)]),
+            ("Firefox", &[], &[]),
+            ("Firefox", &[7, 20], &[]),
+        ];
+
+        for (text, positions, expected) in cases {
+            assert_eq!(ranges(text, positions), *expected, "text {:?} positions {:?}", text, positions);
+        }
+    }
+
+    #[test]
+    fn highlight_ranges_slice_whole_chars() {
+        let text = "a😀微b";
+        let utf16: Vec<u16> = text.encode_utf16().collect();
+
+        for range in highlight_ranges(text, &[1, 2]) {
+            assert_eq!(String::from_utf16(&utf16[range.start..range.end]).unwrap(), "😀微");
+        }
+    }
+}
@@ -0,0 +1,282 @@
+use std::env;
+use std::fs;
+use std::cell::OnceCell;
+use std::collections::HashSet;
+use std::path::{Path, PathBuf};
+
+use crate::{icon_cache, ico, scan, AppInfo};
+use crate::desktop_entry::{self, DesktopEntry};
+use crate::icon_theme::IconLookup;
+use crate::{sandboxed_apps, wine_apps};
+
+const ICON_SIZE: u32 = 48;
+
+pub(crate) struct ScanContext {
+    locales: Vec<String>,
+    desktops: Vec<String>,
+    icons: IconLookup,
+    wine_prefixes: Vec<PathBuf>,
+}
+
+impl ScanContext {
+    pub(crate) fn new() -> Self {
+        Self {
+            locales: desktop_entry::current_locales(),
+            desktops: desktop_entry::current_desktops(),
+            icons: IconLookup::load(),
+            wine_prefixes: wine_apps::prefixes(),
+        }
+    }
+
+    // Returns the 1x icon and, when a sharper source exists, a 2x one for
+    // HiDPI screens. Raster icons are never upscaled, so a 2x render of the
+    // same bitmap would be wasted; SVG and multi-image .ico files can do better.
+    fn resolve_icon(&self, icon: &str) -> (Option<String>, Option<String>) {
+        let render = |source: &Path, size: u32| {
+            icon_cache::global().get_or_insert_with(source, &size.to_string(), |out| {
+                icon_cache::render_icon_file(source, size, out)
+            })
+        };
+
+        let source = match self.icons.find_icon(icon, ICON_SIZE, 1) {
+            Some(source) => source,
+            None => return (None, None),
+        };
+
+        let hidpi_source = self.icons.find_icon(icon, ICON_SIZE, 2)
+            .filter(|hidpi| *hidpi != source || icon_cache::is_svg(hidpi) || ico::is_icon_file(hidpi));
+
+        (
+            render(&source, ICON_SIZE),
+            hidpi_source.and_then(|hidpi| render(&hidpi, ICON_SIZE * 2)),
+        )
+    }
+}
+
+// Ordered from most to least important, so the first file seen for a
+// desktop-file ID shadows every later one.
+fn get_application_dirs() -> Vec<PathBuf> {
+    let mut app_dirs = Vec::new();
+
+    let data_home = env::var("XDG_DATA_HOME")
+        .ok()
+        .filter(|v| !v.is_empty())
+        .map(PathBuf::from)
+        .or_else(|| dirs::home_dir().map(|home| home.join(".local/share")));
+
+    if let Some(data_home) = data_home {
+        app_dirs.push(data_home.join("applications"));
+    }
+
+    let data_dirs = env::var("XDG_DATA_DIRS")
+        .ok()
+        .filter(|v| !v.is_empty())
+        .unwrap_or_else(|| "/usr/local/share:/usr/share".to_string());
+
+    for dir in data_dirs.split(':').filter(|d| !d.is_empty()) {
+        app_dirs.push(PathBuf::from(dir).join("applications"));
+    }
+
+    for dir in sandboxed_apps::export_dirs() {
+        let dir = dir.join("applications");
+        if !app_dirs.contains(&dir) {
+            app_dirs.push(dir);
+        }
+    }
+
+    app_dirs
+}
+
+fn desktop_file_id(applications_dir: &Path, path: &Path) -> Option<String> {
+    let relative = path.strip_prefix(applications_dir).ok()?;
+
+    let parts: Vec<String> = relative.components()
+        .map(|c| c.as_os_str().to_string_lossy().to_string())
+        .collect();
+
+    Some(parts.join("-"))
+}
+
+fn desktop_entry_to_app(entry: &DesktopEntry, path: &Path, context: &ScanContext) -> Option<AppInfo> {
+    let name = entry.localized("Name")?;
+    let exec = entry.string("Exec")?;
+
+    let (icon_path, icon_path_2x) = entry.string("Icon")
+        .map(|icon| context.resolve_icon(&icon))
+        .unwrap_or_default();
+
+    Some(AppInfo {
+        name,
+        path: path.to_string_lossy().to_string(),
+        icon_path,
+        icon_path_2x,
+        is_shortcut: true,
+        generic_name: entry.localized("GenericName"),
+        keywords: entry.localized_list("Keywords"),
+        exec: Some(exec),
+        icon_name: entry.string("Icon"),
+        terminal: entry.boolean("Terminal"),
+        working_dir: entry.string("Path").filter(|p| !p.is_empty()),
+        source: Some(path.to_string_lossy().to_string()),
+        packaging: sandboxed_apps::packaging(entry, path),
+        ..Default::default()
+    })
+}
+
+fn load_desktop_app(path: &Path, context: &ScanContext) -> Option<AppInfo> {
+    let content = fs::read_to_string(path).ok()?;
+    let desktop_entry = DesktopEntry::parse(&content, &context.locales)?;
+
+    if !desktop_entry.should_show(&context.desktops) {
+        return None;
+    }
+
+    if desktop_entry.string("Exec")
+        .is_some_and(|exec| wine_apps::is_indexed_wine_launcher(&exec, &context.wine_prefixes)) {
+        return None;
+    }
+
+    desktop_entry_to_app(&desktop_entry, path, context)
+}
+
+pub(crate) fn get_desktop_entry_apps() -> Vec<AppInfo> {
+    let context = ScanContext::new();
+
+    let mut seen_ids = HashSet::new();
+    let mut apps = Vec::new();
+
+    for dir in get_application_dirs() {
+        if !dir.is_dir() {
+            continue;
+        }
+
+        // Distributions and Nix/Guix profiles populate these dirs with
+        // symlinks, so they are always followed.
+        for entry in scan::walk_with(&dir, None, true)
+            .filter(|e| e.file_type().is_file())
+            .filter(|e| is_desktop_file(e.path())) {
+
+            let path = entry.path();
+
+            // A hidden or filtered-out entry still claims its ID, which is how
+            // users mask system entries from ~/.local/share/applications.
+            let id = match desktop_file_id(&dir, path) {
+                Some(id) => id,
+                None => continue,
+            };
+            if !seen_ids.insert(id) {
+                continue;
+            }
+
+            if let Some(app) = load_desktop_app(path, &context) {
+                apps.push(app);
+            }
+        }
+    }
+
+    apps
+}
+
+fn is_desktop_file(path: &Path) -> bool {
+    path.extension().is_some_and(|ext| ext == "desktop")
+}
+
+pub(crate) fn collect_apps() -> Vec<AppInfo> {
+    get_desktop_entry_apps()
+}
+
+// Takes the desktop files out of `files`, which come from the extra scan
+// roots. Those don't take part in desktop-file ID masking.
+pub(crate) fn root_apps(files: &mut Vec<PathBuf>) -> Vec<AppInfo> {
+    let (desktop_files, rest): (Vec<_>, Vec<_>) = files.drain(..).partition(|path| is_desktop_file(path));
+    *files = rest;
+
+    if desktop_files.is_empty() {
+        return Vec::new();
+    }
+
+    let context = ScanContext::new();
+    desktop_files.iter()
+        .filter_map(|path| load_desktop_app(path, &context))
+        .collect()
+}
+
+pub(crate) fn watch_roots() -> Vec<PathBuf> {
+    get_application_dirs()
+}
+
+// Whether `path` lies in an application dir, whose symlinks a full scan
+// always follows, so a rescan of it has to as well.
+pub(crate) fn in_application_dir(path: &Path) -> bool {
+    get_application_dirs().iter().any(|dir| path.starts_with(dir))
+}
+
+// Re-resolves the desktop-file ID of a changed file across every application
+// dir, since adding or removing a file can unmask an entry lower down.
+// `context` is shared by the files of a watcher batch and only loaded once
+// one of them is a desktop file.
+pub(crate) fn rescan_file(path: &Path, context: &OnceCell<ScanContext>) -> Option<(Vec<String>, Vec<AppInfo>)> {
+    if !is_desktop_file(path) {
+        return None;
+    }
+
+    let dirs = get_application_dirs();
+    let root = dirs.iter().find(|dir| path.starts_with(dir))?;
+    let relative = path.strip_prefix(root).ok()?.to_path_buf();
+    let id = desktop_file_id(root, path)?;
+
+    let mut candidates = vec![relative];
+    if candidates[0] != Path::new(&id) {
+        candidates.push(PathBuf::from(&id));
+    }
+
+    let sources: Vec<PathBuf> = dirs.iter()
+        .flat_map(|dir| candidates.iter().map(move |c| dir.join(c)))
+        .collect();
+
+    let apps = sources.iter()
+        .find(|source| source.is_file())
+        .and_then(|source| load_desktop_app(source, context.get_or_init(ScanContext::new)))
+        .into_iter()
+        .collect();
+
+    Some((sources.iter().map(|s| s.to_string_lossy().to_string()).collect(), apps))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::os::unix::fs::symlink;
+
+    use super::*;
+
+    #[test]
+    fn rescan_follows_symlinked_desktop_files() {
+        let root = env::temp_dir().join(format!("bsearch-linux-apps-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&root);
+
+        // A new subdir of ~/.local/share/applications whose entry links into
+        // a package store, as Nix and flatpak exports do.
+        let target = root.join("store/editor.desktop");
+        let changed_dir = root.join("data/applications/tools");
+        fs::create_dir_all(target.parent().unwrap()).unwrap();
+        fs::create_dir_all(&changed_dir).unwrap();
+        fs::write(&target, "[Desktop Entry]\nType=Application\nName=Editor\nExec=editor %F\n").unwrap();
+        symlink(&target, changed_dir.join("editor.desktop")).unwrap();
+
+        env::set_var("XDG_DATA_HOME", root.join("data"));
+        env::set_var("XDG_DATA_DIRS", root.join("system"));
+
+        // The walk `AppCache::rescan_paths` does for a changed dir.
+        let context = OnceCell::new();
+        let apps: Vec<AppInfo> = scan::walk_with(&changed_dir, None, in_application_dir(&changed_dir))
+            .filter(|e| e.file_type().is_file())
+            .filter_map(|e| rescan_file(e.path(), &context))
+            .flat_map(|(_, apps)| apps)
+            .collect();
+
+        fs::remove_dir_all(&root).unwrap();
+
+        let names: Vec<&str> = apps.iter().map(|app| app.name.as_str()).collect();
+        assert_eq!(names, ["Editor"], "symlinked entry in a new application subdir");
+    }
+}
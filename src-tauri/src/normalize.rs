@@ -0,0 +1,103 @@
+use unicode_normalization::char::{decompose_compatible, is_combining_mark};
+
+// Both names and queries are folded before matching, so "cafe" finds "Café",
+// "ａｂｃ" finds "abc" and "istanbul" finds "İstanbul". Compatibility
+// decomposition splits accented letters into base letter and mark and maps
+// full-width forms to their ASCII counterparts; marks are then dropped and
+// the rest is case folded.
+
+// Case folding beyond what `to_lowercase` does.
+fn fold_case(c: char, out: &mut String) {
+    match c {
+        'ß' | 'ẞ' => out.push_str("ss"),
+        'ı' => out.push('i'),
+        'ς' => out.push('σ'),
+        _ => out.extend(c.to_lowercase().filter(|&c| !is_combining_mark(c))),
+    }
+}
+
+pub fn fold_char(c: char, out: &mut String) {
+    decompose_compatible(c, |c| {
+        if !is_combining_mark(c) {
+            fold_case(c, out);
+        }
+    });
+}
+
+pub fn fold(text: &str) -> String {
+    let mut folded = String::with_capacity(text.len());
+    for c in text.chars() {
+        fold_char(c, &mut folded);
+    }
+    folded
+}
+
+// Folds `text` and returns, for each char of the result, the index of the
+// char of `text` it came from.
+pub fn fold_indexed(text: &str) -> (String, Vec<usize>) {
+    let mut folded = String::with_capacity(text.len());
+    let mut origins = Vec::with_capacity(text.len());
+
+    for (i, c) in text.chars().enumerate() {
+        let start = folded.len();
+        fold_char(c, &mut folded);
+        origins.extend(folded[start..].chars().map(|_| i));
+    }
+
+    (folded, origins)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn folds_accents_case_and_compatibility_forms() {
+        let cases: &[(&str, &str)] = &[
+            ("Café", "cafe"),
+            // Precomposed and combining spellings fold alike.
+            ("Cafe\u{301}", "cafe"),
+            ("Ångström", "angstrom"),
+            ("İstanbul", "istanbul"),
+            ("Straße", "strasse"),
+            ("ΟΔΟΣ", "οδοσ"),
+            ("ｆｉｒｅｆｏｘ １２３", "firefox 123"),
+            ("ＶＳＣｏｄｅ", "vscode"),
+            // Ligatures expand to several chars.
+            ("ﬁle ﬂow", "file flow"),
+            ("Ⅻ", "xii"),
+            ("微信 ２", "微信 2"),
+        ];
+
+        for (text, expected) in cases {
+            assert_eq!(fold(text), *expected, "text {:?}", text);
+        }
+    }
+
+    #[test]
+    fn fold_indexed_matches_fold() {
+        for text in ["Café", "ﬁle", "ｆｉｒｅ", "Straße", "微信", "Cafe\u{301}"] {
+            assert_eq!(fold_indexed(text).0, fold(text), "text {:?}", text);
+        }
+    }
+
+    #[test]
+    fn fold_indexed_keeps_origins_aligned_after_expansion() {
+        let cases: &[(&str, &str, &[usize])] = &[
+            ("Café", "cafe", &[0, 1, 2, 3]),
+            // The dropped combining mark leaves no output char behind.
+            ("Cafe\u{301}!", "cafe!", &[0, 1, 2, 3, 5]),
+            ("aﬁb", "afib", &[0, 1, 1, 2]),
+            ("Straße", "strasse", &[0, 1, 2, 3, 4, 4, 5]),
+            ("ﬃx", "ffix", &[0, 0, 0, 1]),
+            ("ａ微ｂ", "a微b", &[0, 1, 2]),
+        ];
+
+        for (text, folded, origins) in cases {
+            let (actual, actual_origins) = fold_indexed(text);
+            assert_eq!(actual, *folded, "text {:?}", text);
+            assert_eq!(actual_origins, *origins, "origins of {:?}", text);
+            assert_eq!(actual_origins.len(), actual.chars().count(), "one origin per char of {:?}", text);
+        }
+    }
+}
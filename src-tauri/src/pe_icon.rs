@@ -0,0 +1,212 @@
+use std::fs;
+use std::path::Path;
+
+use crate::ico::{self, IconImage};
+
+// Reads icons straight from the resource section of PE files (.exe, .dll),
+// so Windows binaries can be indexed from any OS.
+
+const RT_ICON: u32 = 3;
+const RT_GROUP_ICON: u32 = 14;
+const RESOURCE_DIRECTORY: usize = 2;
+const MAX_DIRECTORY_ENTRIES: usize = 4096;
+
+struct Section {
+    virtual_address: u32,
+    virtual_size: u32,
+    raw_offset: u32,
+    raw_size: u32,
+}
+
+struct PeFile<'a> {
+    data: &'a [u8],
+    sections: Vec<Section>,
+    resource_offset: usize,
+}
+
+// A resource directory entry: either a named or a numeric ID, pointing at a
+// subdirectory or at data.
+struct ResourceEntry {
+    id: Option<u32>,
+    offset: u32,
+    is_dir: bool,
+}
+
+fn u16_at(data: &[u8], offset: usize) -> Option<u16> {
+    Some(u16::from_le_bytes(data.get(offset..offset.checked_add(2)?)?.try_into().ok()?))
+}
+
+fn u32_at(data: &[u8], offset: usize) -> Option<u32> {
+    Some(u32::from_le_bytes(data.get(offset..offset.checked_add(4)?)?.try_into().ok()?))
+}
+
+impl<'a> PeFile<'a> {
+    fn parse(data: &'a [u8]) -> Result<Self, String> {
+        if data.get(0..2) != Some(b"MZ") {
+            return Err("not a PE file".to_string());
+        }
+
+        let pe_offset = u32_at(data, 0x3C).ok_or("truncated DOS header")? as usize;
+        if data.get(pe_offset..pe_offset + 4) != Some(b"PE\0\0") {
+            return Err("missing PE signature".to_string());
+        }
+
+        let coff = pe_offset + 4;
+        let section_count = u16_at(data, coff + 2).ok_or("truncated COFF header")? as usize;
+        let optional_size = u16_at(data, coff + 16).ok_or("truncated COFF header")? as usize;
+        let optional = coff + 20;
+
+        let (rva_count_offset, directories_offset) = match u16_at(data, optional) {
+            Some(0x10B) => (optional + 92, optional + 96),
+            Some(0x20B) => (optional + 108, optional + 112),
+            _ => return Err("unknown optional header".to_string()),
+        };
+
+        let rva_count = u32_at(data, rva_count_offset).ok_or("truncated optional header")? as usize;
+        if rva_count <= RESOURCE_DIRECTORY {
+            return Err("no resource directory".to_string());
+        }
+        let resource_rva = u32_at(data, directories_offset + RESOURCE_DIRECTORY * 8)
+            .ok_or("truncated data directories")?;
+        if resource_rva == 0 {
+            return Err("no resources".to_string());
+        }
+
+        let sections_offset = optional + optional_size;
+        let sections = (0..section_count)
+            .map(|i| {
+                let header = sections_offset + i * 40;
+                Some(Section {
+                    virtual_size: u32_at(data, header + 8)?,
+                    virtual_address: u32_at(data, header + 12)?,
+                    raw_size: u32_at(data, header + 16)?,
+                    raw_offset: u32_at(data, header + 20)?,
+                })
+            })
+            .collect::<Option<Vec<_>>>()
+            .ok_or("truncated section table")?;
+
+        let mut pe = PeFile { data, sections, resource_offset: 0 };
+        pe.resource_offset = pe.rva_to_offset(resource_rva).ok_or("resource section not mapped")?;
+
+        Ok(pe)
+    }
+
+    fn rva_to_offset(&self, rva: u32) -> Option<usize> {
+        // Sections whose offsets overflow are damaged; skip rather than wrap.
+        self.sections.iter()
+            .filter(|s| rva >= s.virtual_address && rva < s.virtual_address.saturating_add(s.virtual_size.max(s.raw_size)))
+            .find_map(|s| (rva - s.virtual_address).checked_add(s.raw_offset))
+            .map(|offset| offset as usize)
+    }
+
+    // Entries of the directory at `offset` (relative to the resource
+    // section), named entries first, as they are stored.
+    fn entries(&self, offset: u32) -> Vec<ResourceEntry> {
+        let dir = self.resource_offset + offset as usize;
+        let named = u16_at(self.data, dir + 12).unwrap_or(0) as usize;
+        let ids = u16_at(self.data, dir + 14).unwrap_or(0) as usize;
+
+        (0..(named + ids).min(MAX_DIRECTORY_ENTRIES))
+            .filter_map(|i| {
+                let entry = dir + 16 + i * 8;
+                let name = u32_at(self.data, entry)?;
+                let target = u32_at(self.data, entry + 4)?;
+                Some(ResourceEntry {
+                    id: (name & 0x8000_0000 == 0).then_some(name),
+                    offset: target & 0x7FFF_FFFF,
+                    is_dir: target & 0x8000_0000 != 0,
+                })
+            })
+            .collect()
+    }
+
+    // Follows a name-level entry down to its first language's data. The
+    // tree is three levels deep; anything deeper is malformed (or a loop).
+    fn first_data(&self, entry: &ResourceEntry) -> Option<&'a [u8]> {
+        let mut entry_offset = entry.offset;
+        let mut is_dir = entry.is_dir;
+
+        for _ in 0..2 {
+            if !is_dir {
+                break;
+            }
+            let first = self.entries(entry_offset).into_iter().next()?;
+            entry_offset = first.offset;
+            is_dir = first.is_dir;
+        }
+        if is_dir {
+            return None;
+        }
+
+        let data_entry = self.resource_offset + entry_offset as usize;
+        let rva = u32_at(self.data, data_entry)?;
+        let size = u32_at(self.data, data_entry + 4)? as usize;
+        let start = self.rva_to_offset(rva)?;
+        self.data.get(start..start.checked_add(size)?)
+    }
+
+    fn type_entries(&self, resource_type: u32) -> Vec<ResourceEntry> {
+        self.entries(0)
+            .into_iter()
+            .find(|entry| entry.id == Some(resource_type) && entry.is_dir)
+            .map(|entry| self.entries(entry.offset))
+            .unwrap_or_default()
+    }
+}
+
+// `index` follows ExtractIcon: the n-th icon group when non-negative,
+// otherwise the group whose resource ID is its absolute value.
+pub fn icon_images(data: &[u8], index: i32) -> Result<Vec<IconImage>, String> {
+    let pe = PeFile::parse(data)?;
+
+    let groups = pe.type_entries(RT_GROUP_ICON);
+    let group = if index >= 0 {
+        groups.get(index as usize)
+    } else {
+        groups.iter().find(|entry| entry.id == Some(index.unsigned_abs()))
+    };
+    let group = group.ok_or("icon group not found")?;
+    let group_data = pe.first_data(group).ok_or("icon group unreadable")?;
+
+    let icons = pe.type_entries(RT_ICON);
+    let count = u16_at(group_data, 4).ok_or("truncated icon group")? as usize;
+
+    let images = (0..count)
+        .filter_map(|i| {
+            // GRPICONDIRENTRY: like an ICONDIRENTRY, but the image is
+            // referenced by RT_ICON ID instead of a file offset.
+            let entry = group_data.get(6 + i * 14..6 + (i + 1) * 14)?;
+            let id = u16_at(entry, 12)? as u32;
+            let icon = icons.iter().find(|icon| icon.id == Some(id))?;
+
+            Some(IconImage {
+                width: ico::entry_dimension(entry[0]),
+                height: ico::entry_dimension(entry[1]),
+                bit_count: u16_at(entry, 6)?,
+                data: pe.first_data(icon)?.to_vec(),
+            })
+        })
+        .collect::<Vec<_>>();
+
+    if images.is_empty() {
+        return Err("icon group has no images".to_string());
+    }
+
+    Ok(images)
+}
+
+pub fn is_pe_file(path: &Path) -> bool {
+    path.extension()
+        .and_then(|ext| ext.to_str())
+        .is_some_and(|ext| ["exe", "dll", "icl", "cpl", "scr", "ocx"].iter().any(|e| ext.eq_ignore_ascii_case(e)))
+}
+
+// Decodes the best image of icon group `index` for `size` and returns it
+// ready to be scaled and saved.
+pub fn load_icon(path: &Path, index: i32, size: u32) -> Option<image::RgbaImage> {
+    let data = fs::read(path).ok()?;
+    let images = icon_images(&data, index).ok()?;
+
+    ico::decode(&ico::best_image(&images, size)?.data)
+}
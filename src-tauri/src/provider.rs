@@ -0,0 +1,217 @@
+use std::collections::HashMap;
+
+use rayon::prelude::*;
+
+use crate::{AppCache, AppFrequencyTracker, AppResult, QueryKnowledge};
+
+const KNOWLEDGE_WEIGHT: f64 = 50.0;
+pub(crate) const MAX_KNOWLEDGE_BONUS: i64 = 10_000;
+const MAX_RESULTS_PER_TYPE: usize = 10;
+
+pub trait SearchProvider: Send + Sync {
+    /// The `type` this provider stamps on its results, used to route `activate`.
+    fn result_type(&self) -> &str;
+
+    fn search(&self, query: &str) -> Vec<(i64, AppResult)>;
+
+    fn activate(&self, result: &AppResult) -> Result<(), String>;
+}
+
+#[derive(Default)]
+pub struct SearchRegistry {
+    providers: Vec<Box<dyn SearchProvider>>,
+    knowledge: Option<QueryKnowledge>,
+}
+
+impl SearchRegistry {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn register<P: SearchProvider + 'static>(mut self, provider: P) -> Self {
+        self.providers.push(Box::new(provider));
+        self
+    }
+
+    pub(crate) fn with_knowledge(mut self, knowledge: QueryKnowledge) -> Self {
+        self.knowledge = Some(knowledge);
+        self
+    }
+
+    pub fn search(&self, query: &str) -> Vec<AppResult> {
+        let mut results: Vec<(i64, AppResult)> = self.providers
+            .par_iter()
+            .flat_map_iter(|provider| provider.search(query))
+            .collect();
+
+        if let Some(knowledge) = &self.knowledge {
+            let knowledge = knowledge.0.lock().unwrap();
+            for (score, result) in results.iter_mut() {
+                *score += ((knowledge.boost(query, &result.path) * KNOWLEDGE_WEIGHT) as i64).min(MAX_KNOWLEDGE_BONUS);
+            }
+        }
+
+        results.sort_by(|a, b| b.0.cmp(&a.0));
+
+        let mut per_type: HashMap<String, usize> = HashMap::new();
+
+        results.into_iter()
+            .map(|(_, result)| result)
+            .filter(|result| {
+                let count = per_type.entry(result.result_type.clone()).or_insert(0);
+                *count += 1;
+                *count <= MAX_RESULTS_PER_TYPE
+            })
+            .collect()
+    }
+
+    pub fn activate(&self, result: &AppResult, query: &str) -> Result<(), String> {
+        let provider = match self.providers.iter().find(|p| p.result_type() == result.result_type) {
+            Some(provider) => provider,
+            None => return Err(format!("No provider for result type: {}", result.result_type))
+        };
+
+        provider.activate(result)?;
+
+        if let Some(knowledge) = &self.knowledge {
+            knowledge.0.lock().unwrap().record(query, &result.path);
+        }
+
+        Ok(())
+    }
+
+    /// Activates the best result the provider of `result_type` has for `query`,
+    /// for actions that skip the result list.
+    pub fn activate_best(&self, result_type: &str, query: &str) -> Result<(), String> {
+        let provider = match self.providers.iter().find(|p| p.result_type() == result_type) {
+            Some(provider) => provider,
+            None => return Err(format!("No provider for result type: {}", result_type))
+        };
+
+        match provider.search(query).into_iter().max_by_key(|(score, _)| *score) {
+            Some((_, result)) => self.activate(&result, query),
+            None => Err(format!("No {} result for: {}", result_type, query))
+        }
+    }
+}
+
+pub struct AppProvider {
+    cache: AppCache,
+    tracker: AppFrequencyTracker,
+}
+
+impl AppProvider {
+    pub(crate) fn new(cache: AppCache, tracker: AppFrequencyTracker) -> Self {
+        Self { cache, tracker }
+    }
+}
+
+impl SearchProvider for AppProvider {
+    fn result_type(&self) -> &str {
+        "app"
+    }
+
+    fn search(&self, query: &str) -> Vec<(i64, AppResult)> {
+        crate::search_app_results(query, &self.cache, &self.tracker)
+    }
+
+    fn activate(&self, result: &AppResult) -> Result<(), String> {
+        crate::launch_app_path(&result.path, &self.cache, &self.tracker)
+    }
+}
+
+pub struct SearchEngine {
+    pub name: String,
+    pub url_template: String,
+    pub icon_path: Option<String>,
+}
+
+impl SearchEngine {
+    pub fn new(name: &str, url_template: &str, icon_path: Option<&str>) -> Self {
+        Self {
+            name: name.to_string(),
+            url_template: url_template.to_string(),
+            icon_path: icon_path.map(|s| s.to_string()),
+        }
+    }
+
+    fn url_for(&self, query: &str) -> String {
+        self.url_template.replace("{query}", &encode_query(query))
+    }
+}
+
+/// Web search suggestions; always ranked below every app match.
+pub struct WebSearchProvider {
+    engines: Vec<SearchEngine>,
+}
+
+impl WebSearchProvider {
+    pub fn new(engines: Vec<SearchEngine>) -> Self {
+        Self { engines }
+    }
+}
+
+impl Default for WebSearchProvider {
+    fn default() -> Self {
+        Self::new(vec![
+            SearchEngine::new("Google", "https://www.google.com/search?q={query}", Some("/google-icon.svg")),
+            SearchEngine::new("Bing", "https://www.bing.com/search?q={query}", Some("/edge-icon.svg")),
+        ])
+    }
+}
+
+impl SearchProvider for WebSearchProvider {
+    fn result_type(&self) -> &str {
+        "web"
+    }
+
+    fn search(&self, query: &str) -> Vec<(i64, AppResult)> {
+        let query = query.trim();
+        if query.is_empty() {
+            return Vec::new();
+        }
+
+        self.engines.iter()
+            .enumerate()
+            .map(|(i, engine)| {
+                let title = format!("搜索 \"{}\" - {}", query, engine.name);
+                let query_start = "搜索 \"".chars().count();
+                let query_positions: Vec<usize> = (query_start..query_start + query.chars().count()).collect();
+
+                (-(i as i64), AppResult {
+                    result_type: "web".to_string(),
+                    title_highlights: crate::highlight_ranges(&title, &query_positions),
+                    title,
+                    path: engine.url_for(query),
+                    icon_path: engine.icon_path.clone(),
+                    icon_path_2x: None,
+                    match_form: None,
+                    path_highlights: Vec::new(),
+                    did_you_mean: false,
+                })
+            })
+            .collect()
+    }
+
+    fn activate(&self, result: &AppResult) -> Result<(), String> {
+        match tauri_plugin_opener::open_url(&result.path, Option::<&str>::None) {
+            Ok(_) => Ok(()),
+            Err(e) => Err(format!("Open URL Failed: {}", e))
+        }
+    }
+}
+
+fn encode_query(query: &str) -> String {
+    let mut encoded = String::with_capacity(query.len());
+
+    for byte in query.bytes() {
+        match byte {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'_' | b'.' | b'!' | b'~' | b'*' | b'\'' | b'(' | b')' => {
+                encoded.push(byte as char);
+            }
+            _ => encoded.push_str(&format!("%{:02X}", byte)),
+        }
+    }
+
+    encoded
+}
@@ -0,0 +1,239 @@
+use std::collections::HashSet;
+
+// The fuzzy matcher accepts any subsequence, so "vsc" matches a dozen names
+// about equally well. Matches are first placed in tiers by how the query
+// lines up with the name, and the fuzzy score only orders matches within a
+// tier.
+
+// Everything else that goes into a result's score (the fuzzy score here,
+// the shortcut, frecency and knowledge bonuses added later) is capped so
+// that together it stays below one step, and so only breaks ties within a
+// tier.
+pub const TIER_STEP: i64 = 1_000_000;
+pub const MAX_FUZZY_BONUS: i64 = 99;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Tier {
+    Fuzzy,
+    // "dio" in "Visual Studio Code".
+    Substring,
+    // "vsc" or "vscode": prefixes of successive words, with camelCase humps
+    // counting as words.
+    Acronym,
+    // "stu" in "Visual Studio Code".
+    WordPrefix,
+    // "vis" in "Visual Studio Code".
+    ExactPrefix,
+}
+
+// Whether char `i` of `chars` starts a word: after a separator, at a
+// lowercase-to-uppercase or letter-to-digit change, and before the last
+// capital of a run followed by lowercase ("XMLParser").
+pub fn is_word_start(chars: &[char], i: usize) -> bool {
+    let c = chars[i];
+    if !c.is_alphanumeric() {
+        return false;
+    }
+    let Some(&prev) = i.checked_sub(1).and_then(|p| chars.get(p)) else {
+        return true;
+    };
+    let next = chars.get(i + 1).copied();
+
+    !prev.is_alphanumeric()
+        || (prev.is_lowercase() && c.is_uppercase())
+        || (prev.is_alphabetic() != c.is_alphabetic())
+        || (prev.is_uppercase() && c.is_uppercase() && next.is_some_and(|next| next.is_lowercase()))
+}
+
+// Words as start offsets and spans of `text`, running from each start to
+// the next start or the next separator.
+fn words<'a>(text: &'a [char], word_starts: &[usize]) -> Vec<(usize, &'a [char])> {
+    word_starts.iter()
+        .enumerate()
+        .map(|(n, &start)| {
+            let limit = word_starts.get(n + 1).copied().unwrap_or(text.len());
+            let end = (start..limit).find(|&i| !text[i].is_alphanumeric()).unwrap_or(limit);
+            (start, &text[start..end])
+        })
+        .collect()
+}
+
+// Splits `query` into non-empty prefixes of `words`, in order, with words
+// allowed to be skipped, and returns the positions those prefixes cover.
+fn match_word_prefixes(words: &[(usize, &[char])], query: &[char]) -> Option<Vec<usize>> {
+    let mut failed = HashSet::new();
+    let mut positions = Vec::new();
+    split_from(words, query, 0, 0, &mut failed, &mut positions).then_some(positions)
+}
+
+// Whether `query[q..]` splits into prefixes of `words[w..]`, longest prefix
+// of the earliest word first. Pushes the covered positions on success.
+// `failed` remembers the (w, q) pairs that can't be split, so no pair is
+// explored twice and names with many repeated letters stay cheap.
+fn split_from(
+    words: &[(usize, &[char])],
+    query: &[char],
+    w: usize,
+    q: usize,
+    failed: &mut HashSet<(usize, usize)>,
+    positions: &mut Vec<usize>,
+) -> bool {
+    if q == query.len() {
+        return true;
+    }
+    if failed.contains(&(w, q)) {
+        return false;
+    }
+
+    for (i, &(start, word)) in words.iter().enumerate().skip(w) {
+        let common = word.iter().zip(&query[q..]).take_while(|(a, b)| a == b).count();
+        for n in (1..=common).rev() {
+            let len = positions.len();
+            positions.extend(start..start + n);
+            if split_from(words, query, i + 1, q + n, failed, positions) {
+                return true;
+            }
+            positions.truncate(len);
+        }
+    }
+
+    failed.insert((w, q));
+    false
+}
+
+// Returns the tier and the matched char positions in `text`, which are empty
+// for fuzzy matches. `text` and `query` are expected in the same case;
+// `word_starts` are char indices into `text`.
+pub fn tier(text: &str, word_starts: &[usize], query: &str) -> (Tier, Vec<usize>) {
+    let text: Vec<char> = text.chars().collect();
+    let query: Vec<char> = query.chars().collect();
+    let span = |start: usize| (start..start + query.len()).collect();
+
+    if text.starts_with(&query) {
+        return (Tier::ExactPrefix, span(0));
+    }
+    if let Some(&start) = word_starts.iter().find(|&&start| text[start..].starts_with(&query)) {
+        return (Tier::WordPrefix, span(start));
+    }
+
+    let letters: Vec<char> = query.iter().copied().filter(|c| !c.is_whitespace()).collect();
+    if let Some(positions) = match_word_prefixes(&words(&text, word_starts), &letters) {
+        return (Tier::Acronym, positions);
+    }
+
+    match text.windows(query.len().max(1)).position(|window| window == query.as_slice()) {
+        Some(start) => (Tier::Substring, span(start)),
+        None => (Tier::Fuzzy, Vec::new()),
+    }
+}
+
+pub fn match_score(tier: Tier, fuzzy_score: i64) -> i64 {
+    tier as i64 * TIER_STEP + (fuzzy_score / 4).clamp(0, MAX_FUZZY_BONUS)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::cmp::Reverse;
+
+    use fuzzy_matcher::skim::SkimMatcherV2;
+
+    use super::*;
+    use crate::search_index::SearchIndex;
+    use crate::{normalize, AppInfo};
+
+    // Names matching `query`, best first, the way app search orders them
+    // before any bonuses.
+    fn ranked<'a>(query: &str, names: &[&'a str]) -> Vec<&'a str> {
+        let apps: Vec<AppInfo> = names.iter()
+            .map(|name| AppInfo { name: name.to_string(), ..Default::default() })
+            .collect();
+        let index = SearchIndex::build(&apps);
+        let matcher = SkimMatcherV2::default();
+        let query = normalize::fold(query);
+
+        let mut matched: Vec<(i64, &str)> = names.iter()
+            .enumerate()
+            .filter_map(|(i, name)| Some((index.find(i, &matcher, &query)?.score, *name)))
+            .collect();
+        matched.sort_by_key(|(score, _)| Reverse(*score));
+        matched.into_iter().map(|(_, name)| name).collect()
+    }
+
+    #[test]
+    fn orders_by_tier() {
+        let cases: &[(&str, &[&str], &[&str])] = &[
+            // Exact prefix, word prefix, substring.
+            ("studio", &["Audiostudio", "Visual Studio Code", "Studio One"], &["Studio One", "Visual Studio Code", "Audiostudio"]),
+            ("code", &["Xcodes Helper", "Visual Studio Code", "Code::Blocks"], &["Code::Blocks", "Visual Studio Code", "Xcodes Helper"]),
+            // Acronyms, including camelCase humps, beat scattered letters.
+            ("vsc", &["Avast Secure Cleaner", "Visual Studio Code"], &["Visual Studio Code", "Avast Secure Cleaner"]),
+            ("vscode", &["Visual Studio Code"], &["Visual Studio Code"]),
+            ("ps", &["Pass", "PhotoShop"], &["PhotoShop", "Pass"]),
+            // A name that is the query outranks one it abbreviates.
+            ("gimp", &["Git Importer Manager Plus", "GIMP"], &["GIMP", "Git Importer Manager Plus"]),
+            // Fuzzy matches come last; non-matches are dropped.
+            ("fx", &["Firefox", "FX Studio", "Chrome"], &["FX Studio", "Firefox"]),
+            // Folded spellings.
+            ("cafe", &["Le Café", "Café Manager"], &["Café Manager", "Le Café"]),
+            // Full pinyin and pinyin initials of Chinese names.
+            ("weixin", &["WeChat", "微信"], &["微信"]),
+            ("wx", &["Wax Editor", "微信"], &["微信", "Wax Editor"]),
+            ("yunyin", &["Yun Player", "网易云音乐"], &["网易云音乐"]),
+            ("wyy", &["Wayyy Tool", "网易云音乐"], &["网易云音乐", "Wayyy Tool"]),
+        ];
+
+        for (query, names, expected) in cases {
+            assert_eq!(ranked(query, names), *expected, "query {:?}", query);
+        }
+    }
+
+    #[test]
+    fn tiers_and_positions() {
+        let cases: &[(&str, &str, Tier, &[usize])] = &[
+            ("visual studio code", "vis", Tier::ExactPrefix, &[0, 1, 2]),
+            ("visual studio code", "stu", Tier::WordPrefix, &[7, 8, 9]),
+            ("visual studio code", "vsc", Tier::Acronym, &[0, 7, 14]),
+            ("visual studio code", "visc", Tier::Acronym, &[0, 1, 2, 14]),
+            ("visual studio code", "v s c", Tier::Acronym, &[0, 7, 14]),
+            ("xmlparser", "xp", Tier::Fuzzy, &[]),
+            ("visual studio code", "dio", Tier::Substring, &[10, 11, 12]),
+            ("visual studio code", "vdc", Tier::Fuzzy, &[]),
+        ];
+
+        for (text, query, tier_expected, positions) in cases {
+            let chars: Vec<char> = text.chars().collect();
+            let word_starts: Vec<usize> = (0..chars.len()).filter(|&i| is_word_start(&chars, i)).collect();
+
+            assert_eq!(tier(text, &word_starts, query), (*tier_expected, positions.to_vec()), "{:?} in {:?}", query, text);
+        }
+    }
+
+    #[test]
+    fn word_starts() {
+        let starts = |text: &str| {
+            let chars: Vec<char> = text.chars().collect();
+            (0..chars.len()).filter(|&i| is_word_start(&chars, i)).collect::<Vec<_>>()
+        };
+
+        assert_eq!(starts("PhotoShop"), [0, 5]);
+        assert_eq!(starts("XMLParser"), [0, 3]);
+        assert_eq!(starts("7zip File"), [0, 1, 5]);
+        assert_eq!(starts("code::blocks"), [0, 6]);
+    }
+
+    #[test]
+    fn fuzzy_score_stays_within_tier() {
+        assert!(match_score(Tier::Fuzzy, i64::MAX) < match_score(Tier::Substring, i64::MIN));
+        assert!(match_score(Tier::WordPrefix, i64::MAX) < match_score(Tier::ExactPrefix, 0));
+    }
+
+    #[test]
+    fn repetitive_names_do_not_backtrack_exponentially() {
+        let text = vec!["aaaa"; 40].join(" ");
+        let chars: Vec<char> = text.chars().collect();
+        let word_starts: Vec<usize> = (0..chars.len()).filter(|&i| is_word_start(&chars, i)).collect();
+
+        assert_eq!(tier(&text, &word_starts, &"a".repeat(40)).0, Tier::Acronym);
+        assert_eq!(tier(&text, &word_starts, &format!("{}b", "a".repeat(40))).0, Tier::Fuzzy);
+    }
+}
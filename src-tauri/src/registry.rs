@@ -0,0 +1,500 @@
+use std::fs;
+use std::collections::HashMap;
+use std::iter::Peekable;
+use std::path::Path;
+use std::str::CharIndices;
+
+// Registry access behind a small trait, so the Uninstall/App Paths logic
+// runs the same against the live Windows registry, Wine's `system.reg` and
+// `user.reg`, and `.reg` exports.
+
+// 32-bit installers on 64-bit Windows (and in 64-bit Wine prefixes) register
+// under WOW6432Node. A 64-bit process opening the plain key only sees the
+// 64-bit view, and text exports list both hives side by side.
+pub const UNINSTALL_KEYS: &[&str] = &[
+    "HKEY_LOCAL_MACHINE\\SOFTWARE\\Microsoft\\Windows\\CurrentVersion\\Uninstall",
+    "HKEY_LOCAL_MACHINE\\SOFTWARE\\WOW6432Node\\Microsoft\\Windows\\CurrentVersion\\Uninstall",
+    "HKEY_CURRENT_USER\\SOFTWARE\\Microsoft\\Windows\\CurrentVersion\\Uninstall",
+];
+pub const APP_PATHS_KEY: &str = "HKEY_LOCAL_MACHINE\\SOFTWARE\\Microsoft\\Windows\\CurrentVersion\\App Paths";
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum RegValue {
+    String(String),
+    // REG_EXPAND_SZ, left unexpanded: what `%ProgramFiles%` means depends on
+    // whose registry this is.
+    ExpandString(String),
+    MultiString(Vec<String>),
+    Dword(u32),
+    Binary(Vec<u8>),
+}
+
+pub trait RegistryReader {
+    // Names of the direct subkeys of `key`, a full path such as
+    // `HKEY_LOCAL_MACHINE\SOFTWARE\...`.
+    fn subkeys(&self, key: &str) -> Vec<String>;
+
+    // `name` is empty for the key's default value.
+    fn value(&self, key: &str, name: &str) -> Option<RegValue>;
+
+    fn string(&self, key: &str, name: &str) -> Option<String> {
+        match self.value(key, name)? {
+            RegValue::String(s) | RegValue::ExpandString(s) => Some(s),
+            _ => None,
+        }
+    }
+}
+
+pub struct UninstallEntry {
+    pub name: String,
+    pub install_location: String,
+    // Path part of DisplayIcon, without quotes or icon index.
+    pub display_icon: Option<String>,
+}
+
+pub struct AppPathEntry {
+    pub name: String,
+    pub path: String,
+}
+
+pub fn uninstall_entries(reader: &impl RegistryReader) -> Vec<UninstallEntry> {
+    UNINSTALL_KEYS.iter()
+        .flat_map(|parent| {
+            reader.subkeys(parent)
+                .into_iter()
+                .filter_map(move |subkey| {
+                    let key = format!("{}\\{}", parent, subkey);
+                    let name = reader.string(&key, "DisplayName")?;
+                    let install_location = reader.string(&key, "InstallLocation")
+                        .filter(|location| !location.is_empty())?;
+                    let display_icon = reader.string(&key, "DisplayIcon")
+                        .map(|icon| icon_location_path(&icon).to_string())
+                        .filter(|icon| !icon.is_empty());
+                    Some(UninstallEntry { name, install_location, display_icon })
+                })
+        })
+        .collect()
+}
+
+// `"C:\App\app.exe",0` -> `C:\App\app.exe`.
+fn icon_location_path(location: &str) -> &str {
+    let location = location.trim();
+    let path = match location.rsplit_once(',') {
+        Some((path, index)) if index.trim().parse::<i32>().is_ok() => path,
+        _ => location,
+    };
+    path.trim().trim_matches('"')
+}
+
+pub fn app_path_entries(reader: &impl RegistryReader) -> Vec<AppPathEntry> {
+    reader.subkeys(APP_PATHS_KEY)
+        .into_iter()
+        .filter_map(|subkey| {
+            let key = format!("{}\\{}", APP_PATHS_KEY, subkey);
+            let path = reader.string(&key, "")?.trim().trim_matches('"').to_string();
+            if path.is_empty() {
+                return None;
+            }
+
+            let name = Path::new(&subkey)
+                .file_stem()
+                .and_then(|s| s.to_str())
+                .unwrap_or(&subkey)
+                .to_string();
+            Some(AppPathEntry { name, path })
+        })
+        .collect()
+}
+
+#[derive(Default)]
+struct KeyData {
+    children: Vec<String>,
+    values: HashMap<String, RegValue>,
+}
+
+// A registry loaded from text: `.reg` exports (REGEDIT4 and Windows Registry
+// Editor 5.00) and Wine's hive files. Lookups are case-insensitive.
+#[derive(Default)]
+pub struct TextRegistry {
+    keys: HashMap<String, KeyData>,
+}
+
+#[derive(Clone, Copy, PartialEq)]
+enum Format {
+    Regedit4,
+    Regedit5,
+    Wine,
+}
+
+fn normalize_root(root: &str) -> &str {
+    match root.to_ascii_uppercase().as_str() {
+        "HKLM" => "HKEY_LOCAL_MACHINE",
+        "HKCU" => "HKEY_CURRENT_USER",
+        "HKCR" => "HKEY_CLASSES_ROOT",
+        "HKU" => "HKEY_USERS",
+        _ => root,
+    }
+}
+
+pub fn decode_utf16(bytes: &[u8]) -> String {
+    let units: Vec<u16> = bytes.chunks_exact(2)
+        .map(|pair| u16::from_le_bytes([pair[0], pair[1]]))
+        .collect();
+    String::from_utf16_lossy(&units)
+}
+
+// Reads up to `max` more digits in `radix` of an escape started by `first`,
+// as Wine writes them: `\x` takes up to four hex digits, `\0`-`\7` up to
+// three octal ones.
+fn escaped_unit(chars: &mut Peekable<CharIndices>, radix: u32, max: usize, first: u32) -> u16 {
+    let mut code = first;
+    for _ in 0..max {
+        match chars.peek().and_then(|(_, c)| c.to_digit(radix)) {
+            Some(digit) => {
+                code = code * radix + digit;
+                chars.next();
+            }
+            None => break,
+        }
+    }
+    code as u16
+}
+
+// Parses `text"rest` up to the closing quote, unescaping the quoted part.
+// Wine writes every non-ASCII UTF-16 unit as a `\xNNNN` escape, so chars
+// outside the BMP arrive as two escaped surrogates.
+fn split_quoted(s: &str) -> Option<(String, &str)> {
+    let mut units: Vec<u16> = Vec::new();
+    let mut chars = s.char_indices().peekable();
+
+    while let Some((i, c)) = chars.next() {
+        match c {
+            '"' => return Some((String::from_utf16_lossy(&units), &s[i + 1..])),
+            '\\' => match chars.next()?.1 {
+                'n' => units.push('\n' as u16),
+                'r' => units.push('\r' as u16),
+                't' => units.push('\t' as u16),
+                'x' => units.push(escaped_unit(&mut chars, 16, 4, 0)),
+                digit @ '0'..='7' => units.push(escaped_unit(&mut chars, 8, 2, digit as u32 - '0' as u32)),
+                other => units.extend_from_slice(other.encode_utf16(&mut [0; 2])),
+            },
+            _ => units.extend_from_slice(c.encode_utf16(&mut [0; 2])),
+        }
+    }
+
+    None
+}
+
+fn parse_hex(data: &str) -> Option<Vec<u8>> {
+    data.split(',')
+        .map(str::trim)
+        .filter(|b| !b.is_empty())
+        .map(|b| u8::from_str_radix(b, 16).ok())
+        .collect()
+}
+
+impl TextRegistry {
+    pub fn load_file(&mut self, path: &Path, root: &str) -> Result<(), String> {
+        let bytes = fs::read(path).map_err(|e| e.to_string())?;
+
+        // regedit writes version 5 exports as UTF-16 with a BOM.
+        let content = match bytes.strip_prefix(&[0xFF, 0xFE]) {
+            Some(utf16) => decode_utf16(utf16),
+            None => String::from_utf8_lossy(&bytes).into_owned(),
+        };
+
+        self.load(&content, root)
+    }
+
+    // `root` is the hive that Wine files are relative to (for example
+    // `HKEY_LOCAL_MACHINE` for `system.reg`); `.reg` exports name it in
+    // every key.
+    pub fn load(&mut self, content: &str, root: &str) -> Result<(), String> {
+        let mut lines = content.trim_start_matches('\u{feff}').lines();
+
+        let format = match lines.next().map(str::trim) {
+            Some("REGEDIT4") => Format::Regedit4,
+            Some("Windows Registry Editor Version 5.00") => Format::Regedit5,
+            Some(header) if header.starts_with("WINE REGISTRY Version") => Format::Wine,
+            _ => return Err("unrecognized registry file header".to_string()),
+        };
+
+        let mut current: Option<String> = None;
+        let mut logical = String::new();
+
+        for line in lines {
+            // Long hex values continue on the next line after a backslash.
+            let line = line.trim();
+            if let Some(continued) = line.strip_suffix('\\') {
+                logical.push_str(continued);
+                continue;
+            }
+            logical.push_str(line);
+            let line = std::mem::take(&mut logical);
+            let line = line.trim();
+
+            if line.is_empty() || line.starts_with(';') || line.starts_with('#') {
+                continue;
+            }
+
+            if let Some(rest) = line.strip_prefix('[') {
+                current = match rest.rfind(']').map(|end| &rest[..end]) {
+                    Some(name) if !name.starts_with('-') => Some(self.add_key(name, root, format)),
+                    _ => None,
+                };
+                continue;
+            }
+
+            let key = match &current {
+                Some(key) => key,
+                None => continue,
+            };
+
+            let (name, value) = match line.strip_prefix('@') {
+                Some(value) => (String::new(), value),
+                None => match line.strip_prefix('"').and_then(split_quoted) {
+                    Some(parsed) => parsed,
+                    None => continue,
+                },
+            };
+
+            if let Some(value) = value.trim_start().strip_prefix('=').and_then(|v| parse_value(v.trim(), format)) {
+                if let Some(data) = self.keys.get_mut(key) {
+                    data.values.insert(name.to_lowercase(), value);
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    // Registers `name` and all of its ancestors, returning the lookup key.
+    fn add_key(&mut self, name: &str, root: &str, format: Format) -> String {
+        let name = if format == Format::Wine {
+            format!("{}\\{}", root, name.replace("\\\\", "\\"))
+        } else {
+            name.to_string()
+        };
+
+        let mut parts = name.split('\\').filter(|p| !p.is_empty());
+        let mut path = normalize_root(parts.next().unwrap_or_default()).to_string();
+        self.keys.entry(path.to_lowercase()).or_default();
+
+        for part in parts {
+            let parent = path.to_lowercase();
+            path = format!("{}\\{}", path, part);
+
+            let lookup = path.to_lowercase();
+            if !self.keys.contains_key(&lookup) {
+                self.keys.entry(parent).or_default().children.push(part.to_string());
+                self.keys.insert(lookup, KeyData::default());
+            }
+        }
+
+        path.to_lowercase()
+    }
+
+    fn key(&self, key: &str) -> Option<&KeyData> {
+        let mut parts = key.split('\\').filter(|p| !p.is_empty());
+        let mut path = normalize_root(parts.next()?).to_string();
+        for part in parts {
+            path.push('\\');
+            path.push_str(part);
+        }
+        self.keys.get(&path.to_lowercase())
+    }
+}
+
+fn parse_value(value: &str, format: Format) -> Option<RegValue> {
+    if value == "-" {
+        return None;
+    }
+
+    if let Some(rest) = value.strip_prefix('"') {
+        return split_quoted(rest).map(|(s, _)| RegValue::String(s));
+    }
+
+    // Wine writes REG_EXPAND_SZ and REG_MULTI_SZ as quoted strings.
+    if let Some(rest) = value.strip_prefix("str(2):\"") {
+        return split_quoted(rest).map(|(s, _)| RegValue::ExpandString(s));
+    }
+    if let Some(rest) = value.strip_prefix("str(7):\"") {
+        return split_quoted(rest).map(|(s, _)| {
+            RegValue::MultiString(s.split('\0').filter(|s| !s.is_empty()).map(str::to_string).collect())
+        });
+    }
+
+    if let Some(hex) = value.strip_prefix("dword:") {
+        return u32::from_str_radix(hex.trim(), 16).ok().map(RegValue::Dword);
+    }
+
+    let (kind, data) = if let Some(data) = value.strip_prefix("hex:") {
+        (3, data)
+    } else {
+        let rest = value.strip_prefix("hex(")?;
+        let (kind, data) = rest.split_once("):")?;
+        (u32::from_str_radix(kind, 16).ok()?, data)
+    };
+    let bytes = parse_hex(data)?;
+
+    // REGEDIT4 stores strings in the ANSI code page, everything else UTF-16.
+    let text = |bytes: &[u8]| {
+        let s = if format == Format::Regedit4 {
+            bytes.iter().map(|&b| b as char).collect()
+        } else {
+            decode_utf16(bytes)
+        };
+        s.trim_end_matches('\0').to_string()
+    };
+
+    Some(match kind {
+        1 => RegValue::String(text(&bytes)),
+        2 => RegValue::ExpandString(text(&bytes)),
+        4 if bytes.len() == 4 => RegValue::Dword(u32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]])),
+        7 => RegValue::MultiString(text(&bytes).split('\0').filter(|s| !s.is_empty()).map(str::to_string).collect()),
+        _ => RegValue::Binary(bytes),
+    })
+}
+
+impl RegistryReader for TextRegistry {
+    fn subkeys(&self, key: &str) -> Vec<String> {
+        self.key(key).map(|data| data.children.clone()).unwrap_or_default()
+    }
+
+    fn value(&self, key: &str, name: &str) -> Option<RegValue> {
+        self.key(key)?.values.get(&name.to_lowercase()).cloned()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn fixture(name: &str, root: &str) -> TextRegistry {
+        let path = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures").join(name);
+        let mut registry = TextRegistry::default();
+        registry.load_file(&path, root).unwrap();
+        registry
+    }
+
+    const FOO: &str = "HKEY_LOCAL_MACHINE\\SOFTWARE\\Microsoft\\Windows\\CurrentVersion\\Uninstall\\Foo";
+
+    #[test]
+    fn regedit4_values() {
+        let registry = fixture("regedit4.reg", "HKEY_LOCAL_MACHINE");
+
+        assert_eq!(registry.string(FOO, "DisplayName").as_deref(), Some("Foo \"Pro\" Edition"));
+        // hex(2) data continued over two lines.
+        assert_eq!(
+            registry.value(FOO, "InstallLocation"),
+            Some(RegValue::ExpandString("%ProgramFiles%\\Foo Pro Edition\\".to_string())),
+        );
+        assert_eq!(registry.value(FOO, "EstimatedSize"), Some(RegValue::Dword(0xa000)));
+        assert_eq!(
+            registry.value(FOO, "Mirrors"),
+            Some(RegValue::MultiString(vec!["first".to_string(), "second".to_string()])),
+        );
+    }
+
+    #[test]
+    fn lookups_ignore_case_and_accept_short_roots() {
+        let registry = fixture("regedit4.reg", "HKEY_LOCAL_MACHINE");
+        let short = "hklm\\software\\microsoft\\windows\\currentversion\\uninstall\\foo";
+
+        assert_eq!(registry.string(short, "displayname"), registry.string(FOO, "DisplayName"));
+        assert!(registry.subkeys("HKLM\\SOFTWARE").contains(&"Microsoft".to_string()));
+    }
+
+    #[test]
+    fn deleted_keys_are_skipped() {
+        let registry = fixture("regedit4.reg", "HKEY_LOCAL_MACHINE");
+
+        assert!(registry.value("HKEY_LOCAL_MACHINE\\SOFTWARE\\Gone", "Ignored").is_none());
+        assert!(!registry.subkeys("HKEY_LOCAL_MACHINE\\SOFTWARE").contains(&"Gone".to_string()));
+    }
+
+    #[test]
+    fn regedit4_entries() {
+        let registry = fixture("regedit4.reg", "HKEY_LOCAL_MACHINE");
+
+        let uninstall = uninstall_entries(&registry);
+        assert_eq!(uninstall.len(), 1);
+        assert_eq!(uninstall[0].name, "Foo \"Pro\" Edition");
+        assert_eq!(uninstall[0].install_location, "%ProgramFiles%\\Foo Pro Edition\\");
+        assert_eq!(uninstall[0].display_icon.as_deref(), Some("C:\\Program Files\\Foo\\foo.exe"));
+
+        let app_paths = app_path_entries(&registry);
+        assert_eq!(app_paths.len(), 1);
+        assert_eq!(app_paths[0].name, "bar");
+        assert_eq!(app_paths[0].path, "C:\\Bar\\bar.exe");
+    }
+
+    #[test]
+    fn regedit5_utf16_export() {
+        let registry = fixture("regedit5.reg", "HKEY_LOCAL_MACHINE");
+        let key = "HKEY_LOCAL_MACHINE\\SOFTWARE\\WOW6432Node\\Microsoft\\Windows\\CurrentVersion\\Uninstall\\{6F1A2B3C-0000-4000-8000-000000000001}";
+
+        assert_eq!(
+            registry.value(key, "Languages"),
+            Some(RegValue::MultiString(vec!["de".to_string(), "en".to_string()])),
+        );
+        assert_eq!(registry.value(key, "NoModify"), Some(RegValue::Dword(1)));
+
+        let uninstall = uninstall_entries(&registry);
+        assert_eq!(uninstall.len(), 1);
+        assert_eq!(uninstall[0].name, "Grafikprogramm für Fotos");
+        assert_eq!(uninstall[0].install_location, "C:\\Program Files (x86)\\Grafik\\");
+        assert_eq!(uninstall[0].display_icon.as_deref(), Some("C:\\Program Files (x86)\\Grafik\\grafik.exe"));
+
+        let app_paths = app_path_entries(&registry);
+        assert_eq!(app_paths.len(), 1);
+        assert_eq!(app_paths[0].name, "grafik");
+    }
+
+    #[test]
+    fn wine_hive() {
+        let registry = fixture("wine_system.reg", "HKEY_LOCAL_MACHINE");
+        let wechat = "HKEY_LOCAL_MACHINE\\Software\\Microsoft\\Windows\\CurrentVersion\\Uninstall\\WeChat";
+        let steam = "HKEY_LOCAL_MACHINE\\SOFTWARE\\WOW6432Node\\Microsoft\\Windows\\CurrentVersion\\Uninstall\\Steam";
+
+        assert_eq!(registry.string(wechat, "DisplayName").as_deref(), Some("微信"));
+        assert_eq!(
+            registry.value(wechat, "InstallLocation"),
+            Some(RegValue::ExpandString("%ProgramFiles%\\Tencent\\WeChat".to_string())),
+        );
+        assert_eq!(registry.value(wechat, "EstimatedSize"), Some(RegValue::Dword(0x30000)));
+        assert_eq!(registry.value(wechat, "Blob"), Some(RegValue::Binary(vec![1, 2, 3, 4])));
+        assert_eq!(
+            registry.value(steam, "Tags"),
+            Some(RegValue::MultiString(vec!["games".to_string(), "store".to_string()])),
+        );
+
+        let mut names: Vec<String> = uninstall_entries(&registry).into_iter().map(|e| e.name).collect();
+        names.sort();
+        assert_eq!(names, ["Steam", "微信"]);
+
+        let app_paths = app_path_entries(&registry);
+        assert_eq!(app_paths.len(), 1);
+        assert_eq!(app_paths[0].path, "C:\\Tools\\tool.exe");
+    }
+
+    #[test]
+    fn wine_escapes() {
+        let (value, rest) = split_quoted("\\x5fae\\x4fe1 \\xd83d\\xde00 \\\\ \\\"\\101\"=x").unwrap();
+
+        assert_eq!(value, "微信 😀 \\ \"A");
+        assert_eq!(rest, "=x");
+    }
+
+    #[test]
+    fn icon_location_strips_quotes_and_index() {
+        assert_eq!(icon_location_path("\"C:\\App\\app.exe\",0"), "C:\\App\\app.exe");
+        assert_eq!(icon_location_path("C:\\App\\app.exe,-101"), "C:\\App\\app.exe");
+        assert_eq!(icon_location_path("C:\\App, Inc\\app.exe"), "C:\\App, Inc\\app.exe");
+    }
+
+    #[test]
+    fn rejects_unknown_headers() {
+        assert!(TextRegistry::default().load("not a registry file", "HKEY_LOCAL_MACHINE").is_err());
+    }
+}
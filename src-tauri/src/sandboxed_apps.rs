@@ -0,0 +1,157 @@
+use std::env;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+use crate::{AppInfo, Packaging};
+use crate::desktop_entry::DesktopEntry;
+use crate::desktop_exec::{self, tokenize_exec, ExecContext};
+
+// Flatpak and Snap export desktop files and icons into their own data
+// directories, which are only on XDG_DATA_DIRS when the session was set up
+// by their profile scripts.
+
+const SNAP_BIN: &str = "/snap/bin";
+const SNAP_DESKTOP_DIR: &str = "/var/lib/snapd/desktop";
+
+fn flatpak_user_dir() -> Option<PathBuf> {
+    env::var("XDG_DATA_HOME")
+        .ok()
+        .filter(|v| !v.is_empty())
+        .map(PathBuf::from)
+        .or_else(|| dirs::home_dir().map(|home| home.join(".local/share")))
+        .map(|data_home| data_home.join("flatpak/exports/share"))
+}
+
+fn flatpak_dirs() -> Vec<PathBuf> {
+    flatpak_user_dir()
+        .into_iter()
+        .chain(std::iter::once(PathBuf::from("/var/lib/flatpak/exports/share")))
+        .collect()
+}
+
+// Data directories (holding `applications` and `icons`) to scan in addition
+// to the XDG ones, user installations first.
+pub(crate) fn export_dirs() -> Vec<PathBuf> {
+    let mut dirs = flatpak_dirs();
+    dirs.push(PathBuf::from(SNAP_DESKTOP_DIR));
+    dirs
+}
+
+fn snap_wrapper(exec: &str) -> Option<String> {
+    tokenize_exec(exec).ok()?
+        .into_iter()
+        .find_map(|token| {
+            Path::new(&token)
+                .strip_prefix(SNAP_BIN).ok()
+                .map(|name| name.to_string_lossy().to_string())
+        })
+}
+
+// Both export their own keys into desktop files; the directory is the
+// fallback for files written without them.
+pub(crate) fn packaging(entry: &DesktopEntry, path: &Path) -> Option<Packaging> {
+    if let Some(app_id) = entry.string("X-Flatpak") {
+        return Some(Packaging::Flatpak { app_id });
+    }
+
+    let exec = entry.string("Exec").unwrap_or_default();
+    if entry.string("X-SnapInstanceName").is_some() || exec.contains(SNAP_BIN) {
+        // The wrapper is `<snap>` or `<snap>.<app>`; desktop files are named
+        // `<snap>_<app>.desktop`.
+        let name = snap_wrapper(&exec).or_else(|| {
+            let stem = path.file_stem()?.to_string_lossy().to_string();
+            Some(match stem.split_once('_') {
+                Some((snap, app)) if snap == app => snap.to_string(),
+                Some((snap, app)) => format!("{}.{}", snap, app),
+                None => stem,
+            })
+        })?;
+        return Some(Packaging::Snap { name });
+    }
+
+    if flatpak_dirs().iter().any(|dir| path.starts_with(dir)) {
+        let app_id = path.file_stem()?.to_string_lossy().to_string();
+        return Some(Packaging::Flatpak { app_id });
+    }
+
+    None
+}
+
+// Arguments the exported Exec line passes to the app itself, with field
+// codes expanded as for any desktop entry (nothing is opened from the
+// launcher) and without Flatpak's `@@` file forwarding markers.
+fn app_arguments(tokens: &[String], context: &ExecContext) -> Vec<String> {
+    let tokens = tokens.iter().filter(|token| !token.starts_with("@@")).cloned();
+    desktop_exec::expand_args(tokens, context)
+}
+
+fn flatpak_command(app_id: &str, exec: Option<&str>, context: &ExecContext) -> Vec<String> {
+    let tokens = exec.and_then(|exec| tokenize_exec(exec).ok()).unwrap_or_default();
+    let id_position = tokens.iter().position(|token| token == app_id);
+
+    let mut command = vec!["flatpak".to_string(), "run".to_string()];
+
+    // Keep the branch, architecture and command the entry was exported for:
+    // one Flatpak can export several entries that differ only there.
+    if let Some(position) = id_position {
+        command.extend(tokens[..position].iter().filter(|token| {
+            ["--branch=", "--arch=", "--command="].iter().any(|option| token.starts_with(option))
+        }).cloned());
+    }
+    command.push(app_id.to_string());
+    if let Some(position) = id_position {
+        command.extend(app_arguments(&tokens[position + 1..], context));
+    }
+
+    command
+}
+
+fn snap_command(name: &str, exec: Option<&str>, context: &ExecContext) -> Vec<String> {
+    let wrapper = Path::new(SNAP_BIN).join(name);
+    let mut command = if wrapper.exists() {
+        vec![wrapper.to_string_lossy().to_string()]
+    } else {
+        vec!["snap".to_string(), "run".to_string(), name.to_string()]
+    };
+
+    let tokens = exec.and_then(|exec| tokenize_exec(exec).ok()).unwrap_or_default();
+    if let Some(position) = tokens.iter().position(|token| token.starts_with(SNAP_BIN)) {
+        command.extend(app_arguments(&tokens[position + 1..], context));
+    }
+
+    command
+}
+
+pub(crate) fn launch_app(app: &AppInfo) -> Result<(), String> {
+    let context = ExecContext {
+        name: &app.name,
+        icon: app.icon_name.as_deref(),
+        desktop_file: &app.path,
+        files: &[],
+    };
+
+    let mut args = match &app.packaging {
+        Some(Packaging::Flatpak { app_id }) => flatpak_command(app_id, app.exec.as_deref(), &context),
+        Some(Packaging::Snap { name }) => snap_command(name, app.exec.as_deref(), &context),
+        // The runtime mounts its own image.
+        Some(Packaging::AppImage) => vec![app.path.clone()],
+        None => return Err(format!("{} is not a packaged app", app.name)),
+    };
+
+    if app.terminal {
+        let mut terminal = desktop_exec::terminal_command()?;
+        terminal.append(&mut args);
+        args = terminal;
+    }
+
+    let mut command = Command::new(&args[0]);
+    command.args(&args[1..]);
+
+    if let Some(working_dir) = &app.working_dir {
+        command.current_dir(working_dir);
+    }
+
+    command.spawn()
+        .map(|_| ())
+        .map_err(|e| format!("Open App Failed: {}", e))
+}
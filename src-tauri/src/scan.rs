@@ -0,0 +1,192 @@
+use std::path::{Path, PathBuf};
+use std::sync::OnceLock;
+
+use walkdir::{DirEntry, WalkDir};
+
+use crate::{config, icon_cache, AppInfo};
+use crate::config::ScanRoot;
+
+// Every directory walk goes through here so the configured excludes and
+// symlink handling apply the same way to each discovery backend, and so the
+// extra scan roots can be handed to whichever backend understands a file.
+
+const EXECUTABLE: &str = "executable";
+const DEFAULT_EXTENSIONS: &[&str] = &["desktop", "lnk", EXECUTABLE];
+
+struct Pattern {
+    // Patterns without a separator only look at the file name.
+    whole_path: bool,
+    chars: Vec<char>,
+}
+
+fn normalize(text: &str) -> Vec<char> {
+    let text = text.replace('\\', "/");
+    if cfg!(windows) {
+        text.to_lowercase().chars().collect()
+    } else {
+        text.chars().collect()
+    }
+}
+
+fn excludes() -> &'static [Pattern] {
+    static EXCLUDES: OnceLock<Vec<Pattern>> = OnceLock::new();
+
+    EXCLUDES.get_or_init(|| {
+        config::get().scan.exclude.iter()
+            .map(|pattern| {
+                let pattern = config::expand_home(pattern).to_string_lossy().to_string();
+                Pattern {
+                    whole_path: pattern.contains(['/', '\\']),
+                    chars: normalize(&pattern),
+                }
+            })
+            .collect()
+    })
+}
+
+// `*` and `?` stay within one path component; `**` spans any number of them,
+// and `**/` may also match none.
+fn glob_match(pattern: &[char], text: &[char]) -> bool {
+    match pattern {
+        [] => text.is_empty(),
+        ['*', '*', '/', rest @ ..] => {
+            glob_match(rest, text)
+                || (0..text.len()).any(|i| text[i] == '/' && glob_match(rest, &text[i + 1..]))
+        }
+        ['*', '*', rest @ ..] => (0..=text.len()).any(|i| glob_match(rest, &text[i..])),
+        ['*', rest @ ..] => {
+            let component = text.iter().position(|&c| c == '/').unwrap_or(text.len());
+            (0..=component).any(|i| glob_match(rest, &text[i..]))
+        }
+        ['?', rest @ ..] => matches!(text, [c, ..] if *c != '/') && glob_match(rest, &text[1..]),
+        [c, rest @ ..] => text.first() == Some(c) && glob_match(rest, &text[1..]),
+    }
+}
+
+pub fn is_excluded(path: &Path) -> bool {
+    let excludes = excludes();
+    if excludes.is_empty() {
+        return false;
+    }
+
+    let full = normalize(&path.to_string_lossy());
+    let name = normalize(&path.file_name().unwrap_or_default().to_string_lossy());
+
+    excludes.iter().any(|pattern| {
+        glob_match(&pattern.chars, if pattern.whole_path { &full } else { &name })
+    })
+}
+
+// Whether `path` or any directory between it and `root` is excluded, which
+// is what a walk of `root` checks before reaching `path`.
+pub fn is_excluded_within(root: &Path, path: &Path) -> bool {
+    path.starts_with(root)
+        && path.ancestors()
+            .take_while(|ancestor| ancestor.starts_with(root))
+            .any(is_excluded)
+}
+
+// Like `walk`, for callers that decide about symlinks themselves.
+pub fn walk_with(root: &Path, max_depth: Option<usize>, follow_symlinks: bool) -> impl Iterator<Item = DirEntry> {
+    let mut walker = WalkDir::new(root).follow_links(follow_symlinks).sort_by_file_name();
+    if let Some(max_depth) = max_depth {
+        walker = walker.max_depth(max_depth);
+    }
+
+    walker.into_iter()
+        .filter_entry(|entry| !is_excluded(entry.path()))
+        .filter_map(|e| e.ok())
+}
+
+// Walks `root` in file name order, skipping excluded files and directories.
+// Depth 1 is the root's own entries.
+pub fn walk(root: &Path, max_depth: Option<usize>) -> impl Iterator<Item = DirEntry> {
+    walk_with(root, max_depth, config::get().scan.follow_symlinks)
+}
+
+fn roots() -> Vec<(PathBuf, &'static ScanRoot)> {
+    config::get().scan.roots.iter()
+        .map(|root| (config::expand_home(&root.path), root))
+        .collect()
+}
+
+pub fn root_paths() -> Vec<PathBuf> {
+    roots().into_iter().map(|(path, _)| path).collect()
+}
+
+#[cfg(unix)]
+fn is_executable(path: &Path) -> bool {
+    use std::os::unix::fs::PermissionsExt;
+
+    path.metadata().is_ok_and(|metadata| metadata.is_file() && metadata.permissions().mode() & 0o111 != 0)
+}
+
+#[cfg(not(unix))]
+fn is_executable(path: &Path) -> bool {
+    path.extension().is_some_and(|ext| {
+        ["exe", "bat", "cmd", "com"].contains(&ext.to_string_lossy().to_lowercase().as_str())
+    })
+}
+
+// Extensions are compared without the dot and ignoring case. A file that no
+// longer exists passes the `executable` filter, so deletions are picked up.
+fn accepts(root: &ScanRoot, path: &Path) -> bool {
+    let extensions: Vec<String> = if root.extensions.is_empty() {
+        DEFAULT_EXTENSIONS.iter().map(|ext| ext.to_string()).collect()
+    } else {
+        root.extensions.iter().map(|ext| ext.trim_start_matches('.').to_lowercase()).collect()
+    };
+
+    let extension = path.extension().map(|ext| ext.to_string_lossy().to_lowercase());
+    if extension.is_some_and(|ext| extensions.contains(&ext)) {
+        return true;
+    }
+
+    extensions.iter().any(|ext| ext == EXECUTABLE) && (!path.exists() || is_executable(path))
+}
+
+// Files in the configured extra roots that pass their extension filter.
+pub fn root_files() -> Vec<PathBuf> {
+    let default_follow = config::get().scan.follow_symlinks;
+
+    roots().into_iter()
+        .filter(|(path, _)| path.is_dir())
+        .flat_map(|(path, root)| {
+            walk_with(&path, root.max_depth, root.follow_symlinks.unwrap_or(default_follow))
+                .filter(|entry| entry.file_type().is_file())
+                .map(|entry| entry.into_path())
+                .filter(|path| accepts(root, path))
+                .collect::<Vec<_>>()
+        })
+        .collect()
+}
+
+// Whether a changed path is one `root_files` would have returned.
+pub fn is_root_file(path: &Path) -> bool {
+    roots().iter().any(|(root_path, root)| {
+        let Ok(relative) = path.strip_prefix(root_path) else {
+            return false;
+        };
+        let depth = relative.components().count();
+
+        root.max_depth.is_none_or(|max_depth| depth <= max_depth)
+            && !is_excluded_within(root_path, path)
+            && accepts(root, path)
+    })
+}
+
+// Files no backend claimed are launched directly.
+pub fn file_apps(files: &[PathBuf]) -> Vec<AppInfo> {
+    files.iter()
+        .filter(|path| path.is_file())
+        .filter_map(|path| {
+            Some(AppInfo {
+                name: path.file_stem()?.to_string_lossy().to_string(),
+                path: path.to_string_lossy().to_string(),
+                icon_path: icon_cache::global().default_icon(),
+                source: Some(path.to_string_lossy().to_string()),
+                ..Default::default()
+            })
+        })
+        .collect()
+}
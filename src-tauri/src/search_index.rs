@@ -0,0 +1,252 @@
+use std::collections::HashMap;
+
+use fuzzy_matcher::FuzzyMatcher;
+use pinyin::ToPinyin;
+use serde::{Serialize, Deserialize};
+
+use crate::{normalize, ranking, AppInfo};
+use crate::typo::TokenIndex;
+
+// Search terms are matched in several spellings: as written (folded by
+// `normalize`), and for names containing Chinese characters also as full
+// pinyin ("weixin") and as the syllables' initials ("wx"). The spellings are
+// computed once whenever the app list changes rather than on every
+// keystroke.
+
+// Typo corrections score below every real match; each edit costs a step.
+const CORRECTED_SCORE: i64 = 150;
+const EDIT_COST: i64 = 50;
+
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum FormKind {
+    Original,
+    Pinyin,
+    Initials,
+}
+
+// One spelling of a term. `origins[i]` is the index of the char of the
+// original term that char `i` of `text` was produced from, so positions in
+// a form map back to the text that is displayed. `word_starts` are the char
+// indices where words begin, for ranking.
+pub struct Form {
+    pub kind: FormKind,
+    pub text: String,
+    pub origins: Vec<usize>,
+    pub word_starts: Vec<usize>,
+}
+
+impl Form {
+    fn new(kind: FormKind) -> Self {
+        Self { kind, text: String::new(), origins: Vec::new(), word_starts: Vec::new() }
+    }
+
+    fn push(&mut self, text: &str, origin: usize, word_start: bool) {
+        if word_start && !text.is_empty() {
+            self.word_starts.push(self.origins.len());
+        }
+        for c in text.chars() {
+            self.text.push(c);
+            self.origins.push(origin);
+        }
+    }
+}
+
+pub fn forms(term: &str) -> Vec<Form> {
+    let mut original = Form::new(FormKind::Original);
+    let mut pinyin = Form::new(FormKind::Pinyin);
+    let mut initials = Form::new(FormKind::Initials);
+    let mut has_pinyin = false;
+
+    let chars: Vec<char> = term.chars().collect();
+    for (i, &c) in chars.iter().enumerate() {
+        let mut folded = String::new();
+        normalize::fold_char(c, &mut folded);
+        let word_start = ranking::is_word_start(&chars, i);
+
+        // Each Chinese character is a word of its own.
+        match c.to_pinyin() {
+            Some(syllable) => {
+                has_pinyin = true;
+                original.push(&folded, i, true);
+                // Pinyin keyboards type ü as v.
+                pinyin.push(&syllable.plain().replace('ü', "v"), i, true);
+                initials.push(syllable.first_letter(), i, true);
+            }
+            None => {
+                original.push(&folded, i, word_start);
+                pinyin.push(&folded, i, word_start);
+                initials.push(&folded, i, word_start);
+            }
+        }
+    }
+
+    if has_pinyin {
+        vec![original, pinyin, initials]
+    } else {
+        vec![original]
+    }
+}
+
+pub struct NameMatch {
+    pub score: i64,
+    pub form: FormKind,
+    // Which of the app's search terms matched; 0 is the name.
+    pub term: usize,
+    // Matched char indices into that term as written, in order.
+    pub positions: Vec<usize>,
+    // Whether the query only matched after correcting typos.
+    pub corrected: bool,
+}
+
+struct WordOccurrence {
+    app: usize,
+    term: usize,
+    form: FormKind,
+    positions: Vec<usize>,
+}
+
+// Alphanumeric runs of a form, with the chars of the term they cover.
+fn form_words(form: &Form) -> Vec<(String, Vec<usize>)> {
+    let mut words: Vec<(String, Vec<usize>)> = Vec::new();
+    let mut in_word = false;
+
+    for (c, &origin) in form.text.chars().zip(&form.origins) {
+        if !c.is_alphanumeric() {
+            in_word = false;
+            continue;
+        }
+        if !in_word {
+            words.push((String::new(), Vec::new()));
+            in_word = true;
+        }
+
+        let (word, positions) = words.last_mut().unwrap();
+        word.push(c);
+        if positions.last() != Some(&origin) {
+            positions.push(origin);
+        }
+    }
+
+    words
+}
+
+// The forms of every search term of every app, each with the index of its
+// term, in the same order as the app list it was built from, plus the words
+// of those forms for typo correction.
+#[derive(Default)]
+pub struct SearchIndex {
+    entries: Vec<Vec<(usize, Form)>>,
+    words: TokenIndex<WordOccurrence>,
+}
+
+impl SearchIndex {
+    pub fn build(apps: &[AppInfo]) -> Self {
+        let entries: Vec<Vec<(usize, Form)>> = apps.iter()
+            .map(|app| {
+                app.search_terms()
+                    .enumerate()
+                    .flat_map(|(term, text)| forms(text).into_iter().map(move |form| (term, form)))
+                    .collect()
+            })
+            .collect();
+
+        // Initials are too short to correct meaningfully.
+        let words = TokenIndex::build(entries.iter().enumerate().flat_map(|(app, forms)| {
+            forms.iter()
+                .filter(|(_, form)| form.kind != FormKind::Initials)
+                .flat_map(move |(term, form)| {
+                    form_words(form).into_iter().map(move |(word, positions)| {
+                        (word, WordOccurrence { app, term: *term, form: form.kind, positions })
+                    })
+                })
+        }));
+
+        Self { entries, words }
+    }
+
+    // Best match over all forms of the app at `index`; `query` is expected
+    // to be folded by `normalize::fold`.
+    pub fn find(&self, index: usize, matcher: &impl FuzzyMatcher, query: &str) -> Option<NameMatch> {
+        self.entries.get(index)?
+            .iter()
+            .filter_map(|(term, form)| {
+                let (fuzzy_score, fuzzy_positions) = matcher.fuzzy_indices(&form.text, query)?;
+                let (tier, tier_positions) = ranking::tier(&form.text, &form.word_starts, query);
+                let positions = if tier_positions.is_empty() { fuzzy_positions } else { tier_positions };
+
+                // Several chars of a form come from one char of the term
+                // when it is transliterated.
+                let mut positions: Vec<usize> = positions.iter().map(|&i| form.origins[i]).collect();
+                positions.dedup();
+
+                Some(NameMatch {
+                    score: ranking::match_score(tier, fuzzy_score),
+                    form: form.kind,
+                    term: *term,
+                    positions,
+                    corrected: false,
+                })
+            })
+            // The first form wins ties, so names written in Latin letters
+            // report their original spelling.
+            .min_by_key(|name_match| std::cmp::Reverse(name_match.score))
+    }
+
+    // Apps with a word close to each word of `query`, for when nothing
+    // matches it as typed. Returns app indices with their matches, fewest
+    // edits first.
+    pub fn corrections(&self, query: &str) -> Vec<(usize, NameMatch)> {
+        let query_words: Vec<&str> = query.split_whitespace().collect();
+        if query_words.is_empty() {
+            return Vec::new();
+        }
+
+        // The closest occurrence of each query word per app, name first on
+        // ties.
+        let closest: Vec<HashMap<usize, (usize, &WordOccurrence)>> = query_words.iter()
+            .map(|word| {
+                let mut closest: HashMap<usize, (usize, &WordOccurrence)> = HashMap::new();
+                for (distance, occurrence) in self.words.corrections(word) {
+                    let key = (distance, occurrence.term);
+                    closest.entry(occurrence.app)
+                        .and_modify(|best| {
+                            if key < (best.0, best.1.term) {
+                                *best = (distance, occurrence);
+                            }
+                        })
+                        .or_insert((distance, occurrence));
+                }
+                closest
+            })
+            .collect();
+
+        let mut corrections: Vec<(usize, usize, NameMatch)> = closest[0].keys()
+            .filter_map(|&app| {
+                let found: Vec<(usize, &WordOccurrence)> = closest.iter()
+                    .map(|words| words.get(&app).copied())
+                    .collect::<Option<_>>()?;
+
+                let distance: usize = found.iter().map(|(distance, _)| distance).sum();
+                let first = found[0].1;
+                let mut positions: Vec<usize> = found.iter()
+                    .filter(|(_, occurrence)| occurrence.term == first.term)
+                    .flat_map(|(_, occurrence)| occurrence.positions.iter().copied())
+                    .collect();
+                positions.sort_unstable();
+                positions.dedup();
+
+                Some((distance, app, NameMatch {
+                    score: (CORRECTED_SCORE - distance as i64 * EDIT_COST).max(0),
+                    form: first.form,
+                    term: first.term,
+                    positions,
+                    corrected: true,
+                }))
+            })
+            .collect();
+
+        corrections.sort_by_key(|(distance, app, _)| (*distance, *app));
+        corrections.into_iter().map(|(_, app, name_match)| (app, name_match)).collect()
+    }
+}
@@ -0,0 +1,455 @@
+use std::fs;
+use std::path::Path;
+
+// Shell Link (.lnk) parsing per [MS-SHLLINK]. Everything is read from the
+// file bytes, so it works the same on any OS.
+
+const HEADER_SIZE: usize = 0x4C;
+const LINK_CLSID: [u8; 16] = [
+    0x01, 0x14, 0x02, 0x00, 0x00, 0x00, 0x00, 0x00,
+    0xC0, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x46,
+];
+
+const HAS_LINK_TARGET_ID_LIST: u32 = 0x0000_0001;
+const HAS_LINK_INFO: u32 = 0x0000_0002;
+const HAS_NAME: u32 = 0x0000_0004;
+const HAS_RELATIVE_PATH: u32 = 0x0000_0008;
+const HAS_WORKING_DIR: u32 = 0x0000_0010;
+const HAS_ARGUMENTS: u32 = 0x0000_0020;
+const HAS_ICON_LOCATION: u32 = 0x0000_0040;
+const IS_UNICODE: u32 = 0x0000_0080;
+const FORCE_NO_LINK_INFO: u32 = 0x0000_0100;
+const HAS_EXP_STRING: u32 = 0x0000_0200;
+const HAS_EXP_ICON: u32 = 0x0000_4000;
+
+const VOLUME_ID_AND_LOCAL_BASE_PATH: u32 = 0x1;
+const COMMON_NETWORK_RELATIVE_LINK_AND_PATH_SUFFIX: u32 = 0x2;
+
+const ENVIRONMENT_VARIABLE_DATA_BLOCK: u32 = 0xA000_0001;
+const ICON_ENVIRONMENT_DATA_BLOCK: u32 = 0xA000_0007;
+
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct ShellLink {
+    pub link_flags: u32,
+    pub file_attributes: u32,
+    pub file_size: u32,
+    pub icon_index: i32,
+    pub show_command: u32,
+    // Path rebuilt from the shell item IDs, when they describe a file system
+    // location (drive letter followed by file entries).
+    pub id_list_path: Option<String>,
+    pub local_base_path: Option<String>,
+    pub network_path: Option<String>,
+    pub description: Option<String>,
+    pub relative_path: Option<String>,
+    pub working_dir: Option<String>,
+    pub arguments: Option<String>,
+    pub icon_location: Option<String>,
+    // Unexpanded (`%ProgramFiles%\...`) forms from the ExtraData blocks.
+    pub env_target: Option<String>,
+    pub env_icon_location: Option<String>,
+}
+
+struct Reader<'a> {
+    data: &'a [u8],
+}
+
+impl<'a> Reader<'a> {
+    fn bytes(&self, offset: usize, len: usize) -> Result<&'a [u8], String> {
+        offset.checked_add(len)
+            .and_then(|end| self.data.get(offset..end))
+            .ok_or_else(|| format!("truncated at offset {:#x}", offset))
+    }
+
+    fn u16(&self, offset: usize) -> Result<u16, String> {
+        let b = self.bytes(offset, 2)?;
+        Ok(u16::from_le_bytes([b[0], b[1]]))
+    }
+
+    fn u32(&self, offset: usize) -> Result<u32, String> {
+        let b = self.bytes(offset, 4)?;
+        Ok(u32::from_le_bytes([b[0], b[1], b[2], b[3]]))
+    }
+}
+
+// ANSI strings are in the creating system's code page, which the file does
+// not record; Latin-1 keeps ASCII paths intact and never fails.
+fn decode_ansi(bytes: &[u8]) -> String {
+    bytes.iter().map(|&b| b as char).collect()
+}
+
+fn decode_utf16(bytes: &[u8]) -> String {
+    let units: Vec<u16> = bytes.chunks_exact(2)
+        .map(|pair| u16::from_le_bytes([pair[0], pair[1]]))
+        .collect();
+    String::from_utf16_lossy(&units)
+}
+
+fn ansi_z(data: &[u8]) -> String {
+    let end = data.iter().position(|&b| b == 0).unwrap_or(data.len());
+    decode_ansi(&data[..end])
+}
+
+fn utf16_z(data: &[u8]) -> String {
+    let end = data.chunks_exact(2)
+        .position(|pair| pair == [0, 0])
+        .map(|units| units * 2)
+        .unwrap_or(data.len() & !1);
+    decode_utf16(&data[..end])
+}
+
+fn non_empty(value: String) -> Option<String> {
+    Some(value).filter(|v| !v.is_empty())
+}
+
+impl ShellLink {
+    pub fn read(path: &Path) -> Result<Self, String> {
+        let data = fs::read(path).map_err(|e| e.to_string())?;
+        Self::parse(&data)
+    }
+
+    pub fn parse(data: &[u8]) -> Result<Self, String> {
+        let reader = Reader { data };
+
+        if reader.u32(0)? as usize != HEADER_SIZE || reader.bytes(4, 16)? != LINK_CLSID {
+            return Err("not a shell link".to_string());
+        }
+
+        let mut link = ShellLink {
+            link_flags: reader.u32(0x14)?,
+            file_attributes: reader.u32(0x18)?,
+            file_size: reader.u32(0x34)?,
+            icon_index: reader.u32(0x38)? as i32,
+            show_command: reader.u32(0x3C)?,
+            ..Default::default()
+        };
+        let flags = link.link_flags;
+        let mut offset = HEADER_SIZE;
+
+        if flags & HAS_LINK_TARGET_ID_LIST != 0 {
+            let size = reader.u16(offset)? as usize;
+            link.id_list_path = parse_id_list(reader.bytes(offset + 2, size)?);
+            offset += 2 + size;
+        }
+
+        if flags & HAS_LINK_INFO != 0 {
+            let size = reader.u32(offset)? as usize;
+            if flags & FORCE_NO_LINK_INFO == 0 {
+                link.parse_link_info(reader.bytes(offset, size)?)?;
+            }
+            offset += size;
+        }
+
+        let unicode = flags & IS_UNICODE != 0;
+        let mut read_string = |present: bool| -> Result<Option<String>, String> {
+            if !present {
+                return Ok(None);
+            }
+            let chars = reader.u16(offset)? as usize;
+            let len = if unicode { chars * 2 } else { chars };
+            let bytes = reader.bytes(offset + 2, len)?;
+            offset += 2 + len;
+            Ok(Some(if unicode { decode_utf16(bytes) } else { decode_ansi(bytes) }))
+        };
+
+        link.description = read_string(flags & HAS_NAME != 0)?;
+        link.relative_path = read_string(flags & HAS_RELATIVE_PATH != 0)?;
+        link.working_dir = read_string(flags & HAS_WORKING_DIR != 0)?;
+        link.arguments = read_string(flags & HAS_ARGUMENTS != 0)?;
+        link.icon_location = read_string(flags & HAS_ICON_LOCATION != 0)?;
+
+        link.parse_extra_data(&reader, offset);
+
+        Ok(link)
+    }
+
+    fn parse_link_info(&mut self, info: &[u8]) -> Result<(), String> {
+        let reader = Reader { data: info };
+        let header_size = reader.u32(4)? as usize;
+        let info_flags = reader.u32(8)?;
+
+        // Optional Unicode offsets only exist in headers of 0x24 bytes or more.
+        let unicode_offset = |field: usize| -> Result<usize, String> {
+            if header_size >= 0x24 {
+                Ok(reader.u32(field)? as usize)
+            } else {
+                Ok(0)
+            }
+        };
+
+        let tail = |offset: usize| info.get(offset..).unwrap_or_default();
+        let string_at = |ansi: usize, unicode: usize| {
+            if unicode != 0 {
+                utf16_z(tail(unicode))
+            } else if ansi != 0 {
+                ansi_z(tail(ansi))
+            } else {
+                String::new()
+            }
+        };
+
+        let suffix = string_at(reader.u32(0x18)? as usize, unicode_offset(0x20)?);
+
+        if info_flags & VOLUME_ID_AND_LOCAL_BASE_PATH != 0 {
+            let base = string_at(reader.u32(0x10)? as usize, unicode_offset(0x1C)?);
+            self.local_base_path = non_empty(base + &suffix);
+        }
+
+        if info_flags & COMMON_NETWORK_RELATIVE_LINK_AND_PATH_SUFFIX != 0 {
+            let net_link = tail(reader.u32(0x14)? as usize);
+            let net = Reader { data: net_link };
+            let net_tail = |offset: usize| net_link.get(offset..).unwrap_or_default();
+
+            // NetNameOffsetUnicode is only present when NetNameOffset > 0x14.
+            let name_offset = net.u32(8)? as usize;
+            let name = if name_offset > 0x14 {
+                utf16_z(net_tail(net.u32(0x14)? as usize))
+            } else {
+                ansi_z(net_tail(name_offset))
+            };
+
+            if !name.is_empty() {
+                self.network_path = Some(if suffix.is_empty() {
+                    name
+                } else {
+                    format!("{}\\{}", name.trim_end_matches('\\'), suffix)
+                });
+            }
+        }
+
+        Ok(())
+    }
+
+    // ExtraData is a list of size-prefixed blocks ending with a block smaller
+    // than four bytes. Damaged trailing blocks are ignored rather than failing
+    // the whole link.
+    fn parse_extra_data(&mut self, reader: &Reader, mut offset: usize) {
+        while let (Ok(size), Ok(signature)) = (reader.u32(offset), reader.u32(offset + 4)) {
+            let size = size as usize;
+            if size < 8 {
+                break;
+            }
+
+            if let Ok(block) = reader.bytes(offset, size) {
+                let target = || -> Option<String> {
+                    // TargetAnsi (260 bytes) followed by TargetUnicode (520 bytes).
+                    let unicode = block.get(8 + 260..8 + 260 + 520).map(utf16_z).unwrap_or_default();
+                    let ansi = block.get(8..8 + 260).map(ansi_z).unwrap_or_default();
+                    non_empty(unicode).or_else(|| non_empty(ansi))
+                };
+
+                match signature {
+                    ENVIRONMENT_VARIABLE_DATA_BLOCK if self.link_flags & HAS_EXP_STRING != 0 => {
+                        self.env_target = target();
+                    }
+                    ICON_ENVIRONMENT_DATA_BLOCK if self.link_flags & HAS_EXP_ICON != 0 => {
+                        self.env_icon_location = target();
+                    }
+                    _ => {}
+                }
+            } else {
+                break;
+            }
+
+            offset += size;
+        }
+    }
+
+    // The most specific absolute target the link records, in the order the
+    // shell itself prefers. May still contain `%VAR%` references.
+    pub fn target(&self) -> Option<&str> {
+        self.env_target.as_deref()
+            .or(self.local_base_path.as_deref())
+            .or(self.id_list_path.as_deref())
+            .or(self.network_path.as_deref())
+    }
+
+    pub fn icon(&self) -> Option<&str> {
+        self.env_icon_location.as_deref()
+            .or(self.icon_location.as_deref())
+            .filter(|location| !location.is_empty())
+    }
+}
+
+// Rebuilds a path from a drive (volume) item followed by file entry items.
+// Anything else (Control Panel, URLs, virtual folders) yields None.
+fn parse_id_list(data: &[u8]) -> Option<String> {
+    let reader = Reader { data };
+    let mut offset = 0;
+    let mut path = String::new();
+
+    loop {
+        let size = reader.u16(offset).ok()? as usize;
+        if size == 0 {
+            break;
+        }
+        let item = reader.bytes(offset, size).ok()?;
+        offset += size;
+
+        let class = *item.get(2)?;
+        match class & 0x70 {
+            // Root folder (My Computer and friends).
+            0x10 => continue,
+            0x20 => path = ansi_z(item.get(3..)?),
+            0x30 if !path.is_empty() => {
+                let name = file_entry_name(item)?;
+                if !path.ends_with('\\') {
+                    path.push('\\');
+                }
+                path.push_str(&name);
+            }
+            _ => return None,
+        }
+    }
+
+    non_empty(path)
+}
+
+fn file_entry_name(item: &[u8]) -> Option<String> {
+    let reader = Reader { data: item };
+    let unicode = item[2] & 0x04 != 0;
+
+    let (short_name, name_end) = if unicode {
+        let name = utf16_z(item.get(14..)?);
+        let end = 14 + (name.encode_utf16().count() + 1) * 2;
+        (name, end)
+    } else {
+        let name = ansi_z(item.get(14..)?);
+        (name.clone(), 14 + name.len() + 1)
+    };
+
+    // The 0xBEEF0004 extension block after the (2-byte aligned) short name
+    // carries the long Unicode name; its position depends on the version.
+    let ext_offset = (name_end + 1) & !1;
+    let long_name = (|| {
+        if reader.u32(ext_offset + 4).ok()? != 0xBEEF_0004 {
+            return None;
+        }
+        let version = reader.u16(ext_offset + 2).ok()?;
+        let name_offset = match version {
+            0..=2 => return None,
+            3..=6 => 20,
+            7 => 38,
+            8 => 42,
+            _ => 46,
+        };
+        non_empty(utf16_z(item.get(ext_offset + name_offset..)?))
+    })();
+
+    long_name.or_else(|| non_empty(short_name))
+}
+
+// Expands `%NAME%` references the way ExpandEnvironmentStrings does,
+// leaving unknown variables as they are.
+pub fn expand_vars<F>(value: &str, lookup: F) -> String
+where
+    F: Fn(&str) -> Option<String>,
+{
+    let mut result = String::with_capacity(value.len());
+    let mut rest = value;
+
+    while let Some(start) = rest.find('%') {
+        result.push_str(&rest[..start]);
+        let after = &rest[start + 1..];
+
+        let end = match after.find('%') {
+            Some(end) => end,
+            None => {
+                result.push_str(&rest[start..]);
+                return result;
+            }
+        };
+
+        let name = &after[..end];
+        match lookup(name).filter(|_| !name.is_empty()) {
+            Some(expanded) => result.push_str(&expanded),
+            None => {
+                result.push('%');
+                result.push_str(name);
+                result.push('%');
+            }
+        }
+        rest = &after[end + 1..];
+    }
+    result.push_str(rest);
+
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn fixture(name: &str) -> ShellLink {
+        let path = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures").join(name);
+        ShellLink::read(&path).unwrap()
+    }
+
+    #[test]
+    fn local_base_path_from_link_info() {
+        let link = fixture("local_path.lnk");
+
+        assert_eq!(link.local_base_path.as_deref(), Some("C:\\Program Files\\Foo\\foo.exe"));
+        assert_eq!(link.target(), Some("C:\\Program Files\\Foo\\foo.exe"));
+        assert_eq!(link.description.as_deref(), Some("Foo Editor"));
+        assert_eq!(link.working_dir.as_deref(), Some("C:\\Program Files\\Foo"));
+        assert_eq!(link.arguments.as_deref(), Some("--new-window"));
+        assert_eq!(link.file_size, 4096);
+    }
+
+    #[test]
+    fn target_id_list_only() {
+        let link = fixture("id_list_only.lnk");
+
+        assert_eq!(link.local_base_path, None);
+        assert_eq!(link.id_list_path.as_deref(), Some("C:\\Program Files\\Foo\\foo.exe"));
+        assert_eq!(link.target(), Some("C:\\Program Files\\Foo\\foo.exe"));
+    }
+
+    #[test]
+    fn relative_path_with_working_dir() {
+        let link = fixture("relative_path.lnk");
+
+        assert_eq!(link.target(), None);
+        assert_eq!(link.relative_path.as_deref(), Some("..\\Bin\\game.exe"));
+        assert_eq!(link.working_dir.as_deref(), Some("C:\\Games\\Quest"));
+    }
+
+    #[test]
+    fn icon_location_with_index() {
+        let link = fixture("icon_location.lnk");
+
+        assert_eq!(link.target(), Some("C:\\Windows\\notepad.exe"));
+        assert_eq!(link.icon(), Some("C:\\Windows\\System32\\shell32.dll"));
+        assert_eq!(link.icon_index, 13);
+    }
+
+    #[test]
+    fn environment_blocks_take_precedence() {
+        let link = fixture("env_vars.lnk");
+
+        assert_eq!(link.local_base_path.as_deref(), Some("C:\\Program Files\\Foo\\foo.exe"));
+        assert_eq!(link.target(), Some("%ProgramFiles%\\Foo\\foo.exe"));
+        assert_eq!(link.icon(), Some("%ProgramFiles%\\Foo\\foo.ico"));
+        assert_eq!(link.icon_index, 2);
+
+        let lookup = |name: &str| (name == "ProgramFiles").then(|| "D:\\Apps".to_string());
+        assert_eq!(expand_vars(link.target().unwrap(), lookup), "D:\\Apps\\Foo\\foo.exe");
+    }
+
+    #[test]
+    fn expand_vars_leaves_unknown_references() {
+        let lookup = |name: &str| (name == "HOME").then(|| "C:\\Users\\me".to_string());
+
+        assert_eq!(expand_vars("%HOME%\\a", lookup), "C:\\Users\\me\\a");
+        assert_eq!(expand_vars("%NOPE%\\a", lookup), "%NOPE%\\a");
+        assert_eq!(expand_vars("100%", lookup), "100%");
+        assert_eq!(expand_vars("%%", lookup), "%%");
+    }
+
+    #[test]
+    fn rejects_other_files() {
+        assert!(ShellLink::parse(b"MZ\x90\x00").is_err());
+        assert!(ShellLink::parse(&[]).is_err());
+    }
+}
@@ -0,0 +1,418 @@
+use std::fs::File;
+use std::io::{self, Read, Seek, SeekFrom, Write};
+
+use flate2::read::ZlibDecoder;
+use ruzstd::decoding::StreamingDecoder;
+
+// Just enough of SquashFS 4.0 to read small files (desktop entries, icons)
+// out of an image embedded at some offset in a larger file, as AppImages
+// are. Images compressed with gzip, xz or zstd can be read; lzo, lz4 and
+// legacy lzma ones cannot.
+
+const MAGIC: &[u8] = b"hsqs";
+// Prefix of the error `Archive::open` returns for images it can't read.
+pub const UNSUPPORTED_COMPRESSION: &str = "unsupported squashfs compression";
+const METADATA_BLOCK_SIZE: usize = 8192;
+const NO_FRAGMENT: u32 = 0xFFFF_FFFF;
+const MAX_SYMLINK_HOPS: usize = 8;
+const MAX_FILE_SIZE: u64 = 16 * 1024 * 1024;
+
+const BASIC_DIR: u16 = 1;
+const BASIC_FILE: u16 = 2;
+const BASIC_SYMLINK: u16 = 3;
+const EXTENDED_DIR: u16 = 8;
+const EXTENDED_FILE: u16 = 9;
+const EXTENDED_SYMLINK: u16 = 10;
+
+#[derive(Clone, Copy)]
+enum Compression {
+    Gzip,
+    Xz,
+    Zstd,
+}
+
+struct Superblock {
+    compression: Compression,
+    block_size: u32,
+    root_inode: u64,
+    inode_table: u64,
+    directory_table: u64,
+    fragment_table: u64,
+}
+
+enum Inode {
+    Directory {
+        block_index: u32,
+        block_offset: u16,
+        size: u32,
+    },
+    File {
+        blocks_start: u64,
+        size: u64,
+        fragment: u32,
+        fragment_offset: u32,
+        block_sizes: Vec<u32>,
+    },
+    Symlink(String),
+    Other,
+}
+
+pub struct Archive {
+    file: File,
+    // Where the image starts in `file`; every offset in it is relative to
+    // this.
+    offset: u64,
+    superblock: Superblock,
+}
+
+fn u16_at(data: &[u8], offset: usize) -> Option<u16> {
+    Some(u16::from_le_bytes(data.get(offset..offset + 2)?.try_into().ok()?))
+}
+
+fn u32_at(data: &[u8], offset: usize) -> Option<u32> {
+    Some(u32::from_le_bytes(data.get(offset..offset + 4)?.try_into().ok()?))
+}
+
+fn u64_at(data: &[u8], offset: usize) -> Option<u64> {
+    Some(u64::from_le_bytes(data.get(offset..offset + 8)?.try_into().ok()?))
+}
+
+fn push_components<'a>(components: &mut Vec<String>, parts: impl Iterator<Item = &'a str>) {
+    for part in parts {
+        match part {
+            "" | "." => {}
+            ".." => {
+                components.pop();
+            }
+            part => components.push(part.to_string()),
+        }
+    }
+}
+
+// Collects decompressed output, failing once it grows past `limit` so a
+// damaged block can't make us allocate without bound.
+struct LimitedWriter {
+    data: Vec<u8>,
+    limit: usize,
+}
+
+impl Write for LimitedWriter {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        if self.data.len() + buf.len() > self.limit {
+            return Err(io::Error::other("block larger than the block size"));
+        }
+        self.data.extend_from_slice(buf);
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+impl Compression {
+    fn from_id(id: u16) -> Option<Self> {
+        match id {
+            1 => Some(Compression::Gzip),
+            4 => Some(Compression::Xz),
+            6 => Some(Compression::Zstd),
+            _ => None,
+        }
+    }
+
+    // Every block is a complete zlib stream, xz stream or zstd frame.
+    fn decompress(self, data: &[u8], max_size: usize) -> Result<Vec<u8>, String> {
+        let mut out = Vec::new();
+        let result = match self {
+            Compression::Gzip => ZlibDecoder::new(data)
+                .take(max_size as u64)
+                .read_to_end(&mut out)
+                .map(|_| out),
+            Compression::Xz => {
+                let mut writer = LimitedWriter { data: out, limit: max_size };
+                lzma_rs::xz_decompress(&mut &data[..], &mut writer)
+                    .map(|_| writer.data)
+                    .map_err(|e| io::Error::other(e.to_string()))
+            }
+            Compression::Zstd => StreamingDecoder::new(data)
+                .map_err(|e| io::Error::other(e.to_string()))
+                .and_then(|decoder| decoder.take(max_size as u64).read_to_end(&mut out))
+                .map(|_| out),
+        };
+
+        result.map_err(|e| format!("corrupt squashfs block: {}", e))
+    }
+}
+
+impl Archive {
+    pub fn open(mut file: File, offset: u64) -> Result<Self, String> {
+        let mut header = [0u8; 96];
+        file.seek(SeekFrom::Start(offset))
+            .and_then(|_| file.read_exact(&mut header))
+            .map_err(|e| e.to_string())?;
+
+        if &header[0..4] != MAGIC {
+            return Err("not a squashfs image".to_string());
+        }
+        if u16_at(&header, 28) != Some(4) {
+            return Err("unsupported squashfs version".to_string());
+        }
+        let compression_id = u16_at(&header, 20).unwrap_or(0);
+        let compression = Compression::from_id(compression_id)
+            .ok_or_else(|| format!("{} {}", UNSUPPORTED_COMPRESSION, compression_id))?;
+
+        let superblock = Superblock {
+            compression,
+            block_size: u32_at(&header, 12).unwrap_or(0),
+            root_inode: u64_at(&header, 32).unwrap_or(0),
+            inode_table: u64_at(&header, 64).unwrap_or(0),
+            directory_table: u64_at(&header, 72).unwrap_or(0),
+            fragment_table: u64_at(&header, 80).unwrap_or(0),
+        };
+        if !superblock.block_size.is_power_of_two() || superblock.block_size > 1024 * 1024 {
+            return Err("invalid squashfs block size".to_string());
+        }
+
+        Ok(Self { file, offset, superblock })
+    }
+
+    fn read_at(&mut self, position: u64, len: usize) -> Result<Vec<u8>, String> {
+        let mut data = vec![0; len];
+        self.file.seek(SeekFrom::Start(self.offset + position))
+            .and_then(|_| self.file.read_exact(&mut data))
+            .map_err(|e| e.to_string())?;
+        Ok(data)
+    }
+
+    // Reads `len` bytes of a metadata stream (inodes, directories, fragment
+    // entries), which is a chain of compressed blocks of up to 8 KiB each.
+    fn read_metadata(&mut self, mut block: u64, offset: usize, len: usize) -> Result<Vec<u8>, String> {
+        let mut data = Vec::new();
+
+        while data.len() < offset + len {
+            let header = self.read_at(block, 2)?;
+            let header = u16::from_le_bytes([header[0], header[1]]);
+            let size = (header & 0x7FFF) as usize;
+            let stored = self.read_at(block + 2, size)?;
+
+            if header & 0x8000 != 0 {
+                data.extend(stored);
+            } else {
+                data.extend(self.superblock.compression.decompress(&stored, METADATA_BLOCK_SIZE)?);
+            }
+            block += 2 + size as u64;
+
+            if size == 0 {
+                return Err("truncated squashfs metadata".to_string());
+            }
+        }
+
+        Ok(data[offset..offset + len].to_vec())
+    }
+
+    fn read_inode(&mut self, reference: u64) -> Result<Inode, String> {
+        let block = self.superblock.inode_table + (reference >> 16);
+        let offset = (reference & 0xFFFF) as usize;
+        let truncated = || "truncated squashfs inode".to_string();
+
+        // The largest fixed part (extended file) is 16 + 40 bytes.
+        let data = self.read_metadata(block, offset, 56)?;
+        let kind = u16_at(&data, 0).ok_or_else(truncated)?;
+        let body = &data[16..];
+
+        let inode = match kind {
+            BASIC_DIR => Inode::Directory {
+                block_index: u32_at(body, 0).ok_or_else(truncated)?,
+                size: u16_at(body, 8).ok_or_else(truncated)? as u32,
+                block_offset: u16_at(body, 10).ok_or_else(truncated)?,
+            },
+            EXTENDED_DIR => Inode::Directory {
+                size: u32_at(body, 4).ok_or_else(truncated)?,
+                block_index: u32_at(body, 8).ok_or_else(truncated)?,
+                block_offset: u16_at(body, 18).ok_or_else(truncated)?,
+            },
+            BASIC_FILE | EXTENDED_FILE => {
+                let (blocks_start, fragment, fragment_offset, size, list_offset) = if kind == BASIC_FILE {
+                    (
+                        u32_at(body, 0).ok_or_else(truncated)? as u64,
+                        u32_at(body, 4).ok_or_else(truncated)?,
+                        u32_at(body, 8).ok_or_else(truncated)?,
+                        u32_at(body, 12).ok_or_else(truncated)? as u64,
+                        32,
+                    )
+                } else {
+                    (
+                        u64_at(body, 0).ok_or_else(truncated)?,
+                        u32_at(body, 28).ok_or_else(truncated)?,
+                        u32_at(body, 32).ok_or_else(truncated)?,
+                        u64_at(body, 8).ok_or_else(truncated)?,
+                        56,
+                    )
+                };
+                if size > MAX_FILE_SIZE {
+                    return Err("file too large".to_string());
+                }
+
+                let block_size = self.superblock.block_size as u64;
+                let block_count = if fragment == NO_FRAGMENT {
+                    size.div_ceil(block_size)
+                } else {
+                    size / block_size
+                } as usize;
+                let list = self.read_metadata(block, offset + list_offset, block_count * 4)?;
+
+                Inode::File {
+                    blocks_start,
+                    size,
+                    fragment,
+                    fragment_offset,
+                    block_sizes: list.chunks_exact(4).map(|c| u32::from_le_bytes([c[0], c[1], c[2], c[3]])).collect(),
+                }
+            }
+            BASIC_SYMLINK | EXTENDED_SYMLINK => {
+                let target_size = u32_at(body, 4).ok_or_else(truncated)? as usize;
+                if target_size > 4096 {
+                    return Err("invalid symlink".to_string());
+                }
+                let target = self.read_metadata(block, offset + 24, target_size)?;
+                Inode::Symlink(String::from_utf8_lossy(&target).to_string())
+            }
+            _ => Inode::Other,
+        };
+
+        Ok(inode)
+    }
+
+    fn read_dir(&mut self, inode: &Inode) -> Result<Vec<(String, u64)>, String> {
+        let (block_index, block_offset, size) = match inode {
+            Inode::Directory { block_index, block_offset, size } => (*block_index, *block_offset, *size),
+            _ => return Err("not a directory".to_string()),
+        };
+
+        // The stored size counts the implicit `.` and `..` entries.
+        let len = (size as usize).saturating_sub(3);
+        let block = self.superblock.directory_table + block_index as u64;
+        let data = self.read_metadata(block, block_offset as usize, len)?;
+
+        let mut entries = Vec::new();
+        let mut position = 0;
+        while position + 12 <= data.len() {
+            let count = u32_at(&data, position).unwrap_or(0) as usize + 1;
+            let start = u32_at(&data, position + 4).unwrap_or(0) as u64;
+            position += 12;
+
+            for _ in 0..count {
+                let (offset, name_size) = match (u16_at(&data, position), u16_at(&data, position + 6)) {
+                    (Some(offset), Some(name_size)) => (offset as u64, name_size as usize + 1),
+                    _ => return Err("truncated squashfs directory".to_string()),
+                };
+                let name = data.get(position + 8..position + 8 + name_size)
+                    .ok_or("truncated squashfs directory")?;
+
+                entries.push((String::from_utf8_lossy(name).to_string(), (start << 16) | offset));
+                position += 8 + name_size;
+            }
+        }
+
+        Ok(entries)
+    }
+
+    // Follows `path` from the root, resolving symlinks (relative or
+    // absolute, both taken as inside the image).
+    fn lookup(&mut self, path: &str) -> Result<Inode, String> {
+        let mut components = Vec::new();
+        push_components(&mut components, path.split('/'));
+        let mut hops = 0;
+
+        'resolve: loop {
+            let mut inode = self.read_inode(self.superblock.root_inode)?;
+
+            for (i, component) in components.iter().enumerate() {
+                let entries = self.read_dir(&inode)?;
+                let reference = entries.iter()
+                    .find(|(name, _)| name == component)
+                    .map(|(_, reference)| *reference)
+                    .ok_or_else(|| format!("{} not found", path))?;
+                inode = self.read_inode(reference)?;
+
+                if let Inode::Symlink(target) = &inode {
+                    hops += 1;
+                    if hops > MAX_SYMLINK_HOPS {
+                        return Err("too many symlinks".to_string());
+                    }
+
+                    let mut resolved = if target.starts_with('/') {
+                        Vec::new()
+                    } else {
+                        components[..i].to_vec()
+                    };
+                    push_components(&mut resolved, target.split('/').chain(components[i + 1..].iter().map(String::as_str)));
+                    components = resolved;
+                    continue 'resolve;
+                }
+            }
+
+            return Ok(inode);
+        }
+    }
+
+    fn read_block(&mut self, position: u64, stored: u32) -> Result<Vec<u8>, String> {
+        let size = (stored & 0x00FF_FFFF) as usize;
+        let data = self.read_at(position, size)?;
+
+        if stored & 0x0100_0000 != 0 {
+            Ok(data)
+        } else {
+            self.superblock.compression.decompress(&data, self.superblock.block_size as usize)
+        }
+    }
+
+    // Names in the root directory.
+    pub fn root_entries(&mut self) -> Result<Vec<String>, String> {
+        let root = self.read_inode(self.superblock.root_inode)?;
+        Ok(self.read_dir(&root)?.into_iter().map(|(name, _)| name).collect())
+    }
+
+    pub fn read_file(&mut self, path: &str) -> Result<Vec<u8>, String> {
+        let (blocks_start, size, fragment, fragment_offset, block_sizes) = match self.lookup(path)? {
+            Inode::File { blocks_start, size, fragment, fragment_offset, block_sizes } => {
+                (blocks_start, size, fragment, fragment_offset, block_sizes)
+            }
+            _ => return Err(format!("{} is not a file", path)),
+        };
+
+        let block_size = self.superblock.block_size as usize;
+        let mut data = Vec::with_capacity(size as usize);
+        let mut position = blocks_start;
+
+        for stored in block_sizes {
+            if stored == 0 {
+                // A sparse block.
+                data.resize(data.len() + block_size, 0);
+                continue;
+            }
+            let mut block = self.read_block(position, stored)?;
+            block.resize(block_size, 0);
+            data.extend(block);
+            position += (stored & 0x00FF_FFFF) as u64;
+        }
+
+        if fragment != NO_FRAGMENT {
+            // The fragment table is an array of pointers to metadata blocks
+            // of 16-byte entries: start, stored size, unused.
+            let pointer = self.read_at(self.superblock.fragment_table + (fragment as u64 / 512) * 8, 8)?;
+            let entry_block = u64_at(&pointer, 0).ok_or("truncated fragment table")?;
+            let entry = self.read_metadata(entry_block, (fragment as usize % 512) * 16, 16)?;
+            let start = u64_at(&entry, 0).ok_or("truncated fragment entry")?;
+            let stored = u32_at(&entry, 8).ok_or("truncated fragment entry")?;
+
+            let block = self.read_block(start, stored)?;
+            let tail = (size as usize) - data.len().min(size as usize);
+            let begin = fragment_offset as usize;
+            data.extend(block.get(begin..begin + tail).ok_or("fragment out of range")?);
+        }
+
+        data.truncate(size as usize);
+        Ok(data)
+    }
+}
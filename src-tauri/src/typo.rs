@@ -0,0 +1,194 @@
+use std::collections::HashMap;
+
+// Typo fallback for queries nothing else matches ("firfox", "chorme"). Every
+// word of every indexed name is stored once, bucketed by length, so a query
+// word is only compared against words whose length is within the allowed
+// edit distance.
+
+struct Token<T> {
+    chars: Vec<char>,
+    occurrences: Vec<T>,
+}
+
+// Words mapped to wherever they occur, described by `T`.
+pub struct TokenIndex<T> {
+    by_length: Vec<Vec<Token<T>>>,
+}
+
+impl<T> Default for TokenIndex<T> {
+    fn default() -> Self {
+        Self { by_length: Vec::new() }
+    }
+}
+
+// Short words are too easy to turn into other words.
+fn max_distance(len: usize) -> usize {
+    match len {
+        0..=2 => 0,
+        3..=5 => 1,
+        _ => 2,
+    }
+}
+
+// Optimal string alignment distance: insertions, deletions, substitutions
+// and swaps of adjacent chars. Gives up with `None` as soon as every
+// alignment costs more than `max`.
+fn bounded_distance(a: &[char], b: &[char], max: usize) -> Option<usize> {
+    if a.len().abs_diff(b.len()) > max {
+        return None;
+    }
+
+    let mut before_previous: Vec<usize> = Vec::new();
+    let mut previous: Vec<usize> = (0..=b.len()).collect();
+
+    for i in 1..=a.len() {
+        let mut row = vec![i; b.len() + 1];
+        for j in 1..=b.len() {
+            let cost = usize::from(a[i - 1] != b[j - 1]);
+            row[j] = (previous[j] + 1).min(row[j - 1] + 1).min(previous[j - 1] + cost);
+            if i > 1 && j > 1 && a[i - 1] == b[j - 2] && a[i - 2] == b[j - 1] {
+                row[j] = row[j].min(before_previous[j - 2] + 1);
+            }
+        }
+
+        if row.iter().min().is_some_and(|&min| min > max) {
+            return None;
+        }
+        before_previous = std::mem::replace(&mut previous, row);
+    }
+
+    Some(previous[b.len()]).filter(|&distance| distance <= max)
+}
+
+impl<T> TokenIndex<T> {
+    pub fn build(words: impl IntoIterator<Item = (String, T)>) -> Self {
+        let mut tokens: HashMap<String, Vec<T>> = HashMap::new();
+        for (word, occurrence) in words {
+            tokens.entry(word).or_default().push(occurrence);
+        }
+
+        let mut index = Self::default();
+        for (word, occurrences) in tokens {
+            let chars: Vec<char> = word.chars().collect();
+            if index.by_length.len() <= chars.len() {
+                index.by_length.resize_with(chars.len() + 1, Vec::new);
+            }
+            index.by_length[chars.len()].push(Token { chars, occurrences });
+        }
+
+        index
+    }
+
+    // Occurrences of words within the allowed distance of `word`, with
+    // their distance.
+    pub fn corrections(&self, word: &str) -> Vec<(usize, &T)> {
+        let word: Vec<char> = word.chars().collect();
+        let max = max_distance(word.len());
+        let lengths = word.len().saturating_sub(max)..=word.len() + max;
+
+        lengths
+            .filter_map(|len| self.by_length.get(len))
+            .flatten()
+            .filter_map(|token| Some((bounded_distance(&word, &token.chars, max)?, token)))
+            .flat_map(|(distance, token)| token.occurrences.iter().map(move |occurrence| (distance, occurrence)))
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use fuzzy_matcher::skim::SkimMatcherV2;
+
+    use super::*;
+    use crate::search_index::SearchIndex;
+    use crate::{normalize, AppInfo};
+
+    fn distance(a: &str, b: &str, max: usize) -> Option<usize> {
+        let a: Vec<char> = a.chars().collect();
+        let b: Vec<char> = b.chars().collect();
+        bounded_distance(&a, &b, max)
+    }
+
+    #[test]
+    fn counts_edits_and_transpositions() {
+        let cases: &[(&str, &str, Option<usize>)] = &[
+            ("firefox", "firefox", Some(0)),
+            ("firfox", "firefox", Some(1)),
+            ("firefoxx", "firefox", Some(1)),
+            ("fitefox", "firefox", Some(1)),
+            // A swap of adjacent chars is one edit, not two.
+            ("chorme", "chrome", Some(1)),
+            ("fierfox", "firefox", Some(1)),
+            ("ab", "ba", Some(1)),
+            ("chorem", "chrome", Some(2)),
+            ("微言", "微信", Some(1)),
+            ("", "gimp", None),
+        ];
+
+        for (a, b, expected) in cases {
+            assert_eq!(distance(a, b, 2), *expected, "{:?} -> {:?}", a, b);
+            assert_eq!(distance(b, a, 2), *expected, "{:?} -> {:?}", b, a);
+        }
+    }
+
+    #[test]
+    fn gives_up_past_the_bound() {
+        assert_eq!(distance("chorem", "chrome", 1), None);
+        assert_eq!(distance("firefox", "chrome", 2), None);
+        // Caught by the length check before any row is computed.
+        assert_eq!(distance("vlc", "libreoffice", 2), None);
+        // Every alignment already costs too much after the first rows.
+        assert_eq!(distance("xyzfirefox", "abcfirefox", 2), None);
+        assert_eq!(distance("xyzfirefox", "abcfirefox", 3), Some(3));
+    }
+
+    #[test]
+    fn allows_more_edits_for_longer_words() {
+        let cases: &[(usize, usize)] = &[(0, 0), (1, 0), (2, 0), (3, 1), (5, 1), (6, 2), (20, 2)];
+
+        for (len, expected) in cases {
+            assert_eq!(max_distance(*len), *expected, "length {}", len);
+        }
+    }
+
+    #[test]
+    fn corrects_only_words_of_similar_length() {
+        let index = TokenIndex::build([("firefox", 0), ("fire", 1), ("vlc", 2), ("vim", 3)].map(|(w, i)| (w.to_string(), i)));
+
+        let found = |word: &str| -> Vec<(usize, i32)> {
+            let mut found: Vec<(usize, i32)> = index.corrections(word).into_iter().map(|(d, &i)| (d, i)).collect();
+            found.sort();
+            found
+        };
+
+        assert_eq!(found("firefxo"), [(1, 0)]);
+        assert_eq!(found("fier"), [(1, 1)]);
+        // Three-letter words get one edit, and two-letter ones none.
+        assert_eq!(found("vom"), [(1, 3)]);
+        assert_eq!(found("vi"), []);
+    }
+
+    #[test]
+    fn flags_did_you_mean_only_on_the_fallback() {
+        let apps: Vec<AppInfo> = ["Firefox", "Google Chrome"].iter()
+            .map(|name| AppInfo { name: name.to_string(), ..Default::default() })
+            .collect();
+        let index = SearchIndex::build(&apps);
+        let matcher = SkimMatcherV2::default();
+
+        for query in ["fire", "chrome", "gc"] {
+            let query = normalize::fold(query);
+            let matched = apps.iter().enumerate().find_map(|(i, _)| index.find(i, &matcher, &query));
+            assert!(matched.is_some_and(|m| !m.corrected), "query {:?} matches as typed", query);
+        }
+
+        for (query, expected) in [("fierfox", 0), ("chorme", 1), ("googel chrmoe", 1)] {
+            let query = normalize::fold(query);
+            assert!((0..apps.len()).all(|i| index.find(i, &matcher, &query).is_none()), "query {:?} has no direct match", query);
+
+            let corrections = index.corrections(&query);
+            assert_eq!(corrections.first().map(|(app, _)| *app), Some(expected), "query {:?}", query);
+            assert!(corrections.iter().all(|(_, m)| m.corrected), "query {:?} is flagged as corrected", query);
+        }
+    }
+}
@@ -0,0 +1,153 @@
+use std::path::{Path, PathBuf};
+use std::collections::HashSet;
+use std::sync::mpsc::{self, RecvTimeoutError};
+use std::time::{Duration, Instant};
+
+use notify::{Event, RecommendedWatcher, RecursiveMode, Watcher};
+
+use crate::AppCache;
+
+// Installers tend to touch several files in quick succession; wait for a
+// short quiet period, but never hold a batch back longer than MAX_DELAY.
+const QUIET_PERIOD: Duration = Duration::from_millis(250);
+const MAX_DELAY: Duration = Duration::from_millis(800);
+
+#[derive(Default)]
+struct Batch {
+    paths: HashSet<PathBuf>,
+    needs_rescan: bool,
+}
+
+impl Batch {
+    fn add(&mut self, event: notify::Result<Event>) {
+        match event {
+            Ok(event) if event.need_rescan() => self.needs_rescan = true,
+            Ok(event) if event.kind.is_access() => {}
+            Ok(event) => self.paths.extend(event.paths),
+            Err(e) => {
+                eprintln!("File watcher error: {}", e);
+                self.needs_rescan = true;
+            }
+        }
+    }
+}
+
+fn nearest_existing_ancestor(path: &Path) -> Option<PathBuf> {
+    path.ancestors().skip(1).find(|ancestor| ancestor.is_dir()).map(Path::to_path_buf)
+}
+
+// Roots often don't exist yet: ~/.local/share/applications appears with the
+// first user-installed app, ~/Applications with the first AppImage. Until
+// they do, their nearest existing ancestor is watched non-recursively so
+// their creation is noticed.
+struct Roots {
+    watcher: RecommendedWatcher,
+    watched: Vec<PathBuf>,
+    missing: Vec<PathBuf>,
+    stand_ins: HashSet<PathBuf>,
+}
+
+impl Roots {
+    fn new(watcher: RecommendedWatcher, roots: Vec<PathBuf>) -> Self {
+        let mut roots = Self {
+            watcher,
+            watched: Vec::new(),
+            missing: roots,
+            stand_ins: HashSet::new(),
+        };
+        roots.refresh();
+        roots
+    }
+
+    // Starts watching the missing roots that have appeared and returns them;
+    // moves the stand-in watches closer for the ones still missing.
+    fn refresh(&mut self) -> Vec<PathBuf> {
+        let (appeared, missing): (Vec<PathBuf>, Vec<PathBuf>) = std::mem::take(&mut self.missing)
+            .into_iter()
+            .partition(|root| root.is_dir());
+        self.missing = missing;
+
+        for root in &appeared {
+            match self.watcher.watch(root, RecursiveMode::Recursive) {
+                Ok(()) => self.watched.push(root.clone()),
+                Err(e) => eprintln!("Failed to watch {}: {}", root.display(), e),
+            }
+        }
+
+        let wanted: HashSet<PathBuf> = self.missing.iter()
+            .filter_map(|root| nearest_existing_ancestor(root))
+            .collect();
+        for stale in self.stand_ins.difference(&wanted) {
+            let _ = self.watcher.unwatch(stale);
+        }
+        for stand_in in wanted.difference(&self.stand_ins) {
+            if let Err(e) = self.watcher.watch(stand_in, RecursiveMode::NonRecursive) {
+                eprintln!("Failed to watch {}: {}", stand_in.display(), e);
+            }
+        }
+        self.stand_ins = wanted;
+
+        appeared
+    }
+
+    // Events from stand-in watches only matter for `refresh`.
+    fn is_watched(&self, path: &Path) -> bool {
+        self.watched.iter().any(|root| path.starts_with(root))
+    }
+}
+
+// Runs the initial reconcile and then every incremental update on a single
+// thread, so full and partial rescans never race each other.
+pub(crate) fn start(app_cache: AppCache) {
+    let (tx, rx) = mpsc::channel::<notify::Result<Event>>();
+
+    let roots = match notify::recommended_watcher(tx) {
+        Ok(watcher) => Some(Roots::new(watcher, crate::watch_roots())),
+        Err(e) => {
+            eprintln!("Failed to create file watcher: {}", e);
+            None
+        }
+    };
+
+    std::thread::spawn(move || {
+        app_cache.rescan_all();
+
+        let Some(mut roots) = roots else {
+            return;
+        };
+
+        while let Ok(first) = rx.recv() {
+            let mut batch = Batch::default();
+            batch.add(first);
+
+            let deadline = Instant::now() + MAX_DELAY;
+            loop {
+                let remaining = deadline.saturating_duration_since(Instant::now());
+                if remaining.is_zero() {
+                    break;
+                }
+
+                match rx.recv_timeout(QUIET_PERIOD.min(remaining)) {
+                    Ok(event) => batch.add(event),
+                    Err(RecvTimeoutError::Timeout) => break,
+                    Err(RecvTimeoutError::Disconnected) => return,
+                }
+            }
+
+            // Anything created in a root before its watch was in place is
+            // picked up by rescanning the whole root.
+            batch.paths.extend(roots.refresh());
+
+            if batch.needs_rescan {
+                app_cache.rescan_all();
+            } else {
+                let paths: Vec<PathBuf> = batch.paths.into_iter()
+                    .filter(|path| roots.is_watched(path))
+                    .collect();
+                if !paths.is_empty() {
+                    app_cache.rescan_paths(&paths);
+                }
+            }
+        }
+    });
+}
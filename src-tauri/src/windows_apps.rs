@@ -0,0 +1,424 @@
+use std::env;
+use std::fs::File;
+use std::ptr::null_mut;
+use std::process::Command;
+use std::path::{Path, PathBuf};
+
+use winreg::enums::*;
+use winreg::RegKey;
+
+use windows::core::PCWSTR;
+use windows::Win32::Foundation::COLORREF;
+use windows::Win32::System::Com::CoInitialize;
+
+use image::ImageOutputFormat;
+use crate::{exe_select, icon_cache, scan, AppInfo};
+use crate::registry::{self, RegValue, RegistryReader};
+use crate::shell_link::{expand_vars, ShellLink};
+
+const ICON_SIZE: u32 = 48;
+
+fn get_special_folders() -> Vec<PathBuf> {
+    let mut folders = Vec::new();
+    
+    if let Ok(userprofile) = env::var("USERPROFILE") {
+        folders.push(PathBuf::from(userprofile).join("Desktop"));
+    }
+    
+    if let Ok(public) = env::var("PUBLIC") {
+        folders.push(PathBuf::from(public).join("Desktop"));
+    }
+    
+    if let Ok(appdata) = env::var("APPDATA") {
+        folders.push(PathBuf::from(appdata).join("Microsoft\\Windows\\Start Menu\\Programs"));
+    }
+    
+    if let Ok(programdata) = env::var("ProgramData") {
+        folders.push(PathBuf::from(programdata).join("Microsoft\\Windows\\Start Menu\\Programs"));
+    }
+    
+    folders
+}
+
+fn is_shortcut_file(path: &Path) -> bool {
+    path.extension().map_or(false, |ext| ext.to_string_lossy().to_lowercase() == "lnk")
+}
+
+fn expand_env_vars(value: &str) -> String {
+    expand_vars(value, |name| env::var(name).ok())
+}
+
+fn shortcut_to_app(path: &Path) -> Option<AppInfo> {
+    let link = match ShellLink::read(path) {
+        Ok(link) => link,
+        Err(e) => {
+            eprintln!("Failed to read shortcut {}: {}", path.display(), e);
+            return None;
+        }
+    };
+    
+    let target_path = match link.target() {
+        Some(target) => expand_env_vars(target),
+        None => {
+            let relative = link.relative_path.as_ref()?;
+            path.parent()?.join(relative).to_string_lossy().to_string()
+        }
+    };
+    
+    let name = path.file_stem()
+        .and_then(|s| s.to_str())
+        .unwrap_or("Unknown")
+        .to_string();
+    
+    let icon_name = link.icon().map(expand_env_vars);
+    
+    Some(AppInfo {
+        name,
+        path: target_path,
+        icon_path: None,
+        is_shortcut: true,
+        arguments: link.arguments.filter(|args| !args.is_empty()),
+        working_dir: link.working_dir.filter(|dir| !dir.is_empty()).map(|dir| expand_env_vars(&dir)),
+        icon_name,
+        icon_index: link.icon_index,
+        source: Some(path.to_string_lossy().to_string()),
+        ..Default::default()
+    })
+}
+
+fn get_shortcuts_from_special_folders() -> Vec<AppInfo> {
+    let mut shortcuts = Vec::new();
+    let special_folders = get_special_folders();
+    
+    for folder in special_folders {
+        if !folder.exists() {
+            continue;
+        }
+        
+        for entry in scan::walk(&folder, None)
+            .filter(|e| is_shortcut_file(e.path())) {
+            
+            if let Some(app) = shortcut_to_app(entry.path()) {
+                shortcuts.push(app);
+            }
+        }
+    }
+    
+    shortcuts
+}
+
+// The live registry, read through winreg. Keys are full paths starting
+// with the hive name.
+struct LiveRegistry;
+
+impl LiveRegistry {
+    fn open(&self, key: &str) -> Option<RegKey> {
+        let (root, path) = key.split_once('\\').unwrap_or((key, ""));
+        let hkey = match root.to_ascii_uppercase().as_str() {
+            "HKEY_LOCAL_MACHINE" | "HKLM" => HKEY_LOCAL_MACHINE,
+            "HKEY_CURRENT_USER" | "HKCU" => HKEY_CURRENT_USER,
+            "HKEY_CLASSES_ROOT" | "HKCR" => HKEY_CLASSES_ROOT,
+            "HKEY_USERS" | "HKU" => HKEY_USERS,
+            _ => return None,
+        };
+        RegKey::predef(hkey).open_subkey(path).ok()
+    }
+}
+
+impl RegistryReader for LiveRegistry {
+    fn subkeys(&self, key: &str) -> Vec<String> {
+        self.open(key)
+            .map(|key| key.enum_keys().flatten().collect())
+            .unwrap_or_default()
+    }
+
+    fn value(&self, key: &str, name: &str) -> Option<RegValue> {
+        let raw = self.open(key)?.get_raw_value(name).ok()?;
+        let text = || registry::decode_utf16(&raw.bytes).trim_end_matches('\0').to_string();
+        
+        Some(match raw.vtype {
+            REG_SZ => RegValue::String(text()),
+            REG_EXPAND_SZ => RegValue::ExpandString(text()),
+            REG_MULTI_SZ => RegValue::MultiString(
+                text().split('\0').filter(|s| !s.is_empty()).map(str::to_string).collect()
+            ),
+            REG_DWORD if raw.bytes.len() == 4 => {
+                RegValue::Dword(u32::from_le_bytes([raw.bytes[0], raw.bytes[1], raw.bytes[2], raw.bytes[3]]))
+            }
+            _ => RegValue::Binary(raw.bytes),
+        })
+    }
+}
+
+fn get_installed_apps_from_registry() -> Vec<AppInfo> {
+    let mut apps = Vec::new();
+    
+    for entry in registry::app_path_entries(&LiveRegistry) {
+        apps.push(AppInfo {
+            name: entry.name,
+            path: expand_env_vars(&entry.path),
+            icon_path: None,
+            is_shortcut: false,
+            ..Default::default()
+        });
+    }
+    
+    for entry in registry::uninstall_entries(&LiveRegistry) {
+        let location_path = PathBuf::from(expand_env_vars(&entry.install_location));
+        let display_icon = entry.display_icon.as_deref().map(|icon| PathBuf::from(expand_env_vars(icon)));
+        let candidates = exe_select::candidates(&location_path);
+        
+        if let Some(selection) = exe_select::select(&candidates, &entry.name, display_icon.as_deref()) {
+            apps.push(AppInfo {
+                name: entry.name,
+                path: selection.path.to_string_lossy().to_string(),
+                icon_path: None,
+                is_shortcut: false,
+                selection_rationale: Some(selection.rationale),
+                ..Default::default()
+            });
+        }
+    }
+    
+    apps
+}
+
+pub(crate) fn extract_icon_from_exe(exe_path: &str) -> Option<String> {
+    extract_icon(exe_path, 0, ICON_SIZE).or_else(|| icon_cache::global().default_icon())
+}
+
+// `index` follows ExtractIcon: the n-th icon when non-negative, otherwise
+// the icon whose resource ID is its absolute value. Icons are read from the
+// file's resources directly; GDI is only a fallback for formats we can't
+// parse, and only at the base size.
+fn extract_icon(source_path: &str, index: i32, size: u32) -> Option<String> {
+    let source = Path::new(source_path);
+    let variant = format!("icon-{}-{}", size, index);
+    
+    icon_cache::global().get_or_insert_with(source, &variant, |icon_path| {
+        icon_cache::render_icon_resource(source, index, size, icon_path)
+            || (size == ICON_SIZE && render_exe_icon(source_path, index, icon_path))
+    })
+}
+
+fn app_icons(app: &AppInfo) -> (Option<String>, Option<String>) {
+    let (source, index) = match &app.icon_name {
+        Some(location) => (location.as_str(), app.icon_index),
+        None => (app.path.as_str(), 0),
+    };
+    
+    let icon = extract_icon(source, index, ICON_SIZE).or_else(|| icon_cache::global().default_icon());
+    let icon_2x = extract_icon(source, index, ICON_SIZE * 2);
+    
+    (icon, icon_2x)
+}
+
+fn render_exe_icon(exe_path: &str, index: i32, icon_path: &Path) -> bool {
+    unsafe {
+        let _ = CoInitialize(None);
+        
+        let wide_path: Vec<u16> = exe_path.encode_utf16().chain(std::iter::once(0)).collect();
+        
+        let hicon = windows::Win32::UI::Shell::ExtractIconW(
+            Some(windows::Win32::Foundation::HINSTANCE(null_mut())),
+            PCWSTR(wide_path.as_ptr()),
+            index as u32
+        );
+        
+        if !hicon.is_invalid() {
+            let hdc = windows::Win32::Graphics::Gdi::GetDC(None);
+            if hdc.is_invalid() {
+                let _ = windows::Win32::UI::WindowsAndMessaging::DestroyIcon(hicon);
+                return false;
+            }
+            
+            let hdc_mem = windows::Win32::Graphics::Gdi::CreateCompatibleDC(Some(hdc));
+            if hdc_mem.is_invalid() {
+                let _ = windows::Win32::Graphics::Gdi::ReleaseDC(None, hdc);
+                let _ = windows::Win32::UI::WindowsAndMessaging::DestroyIcon(hicon);
+                return false;
+            }
+            
+            let hbmp = windows::Win32::Graphics::Gdi::CreateCompatibleBitmap(hdc, 48, 48);
+            if hbmp.is_invalid() {
+                let _ = windows::Win32::Graphics::Gdi::DeleteDC(hdc_mem);
+                let _ = windows::Win32::Graphics::Gdi::ReleaseDC(None, hdc);
+                let _ = windows::Win32::UI::WindowsAndMessaging::DestroyIcon(hicon);
+                return false;
+            }
+            
+            let old_obj = windows::Win32::Graphics::Gdi::SelectObject(hdc_mem, hbmp.into());
+            
+            let _ = windows::Win32::Graphics::Gdi::SetBkColor(hdc_mem, COLORREF(0x00FFFFFF));
+            let _ = windows::Win32::Graphics::Gdi::SetBkMode(hdc_mem, windows::Win32::Graphics::Gdi::TRANSPARENT);
+            
+            let _ = windows::Win32::UI::WindowsAndMessaging::DrawIconEx(
+                hdc_mem,
+                0, 0,
+                hicon,
+                48, 48,
+                0,
+                None,
+                windows::Win32::UI::WindowsAndMessaging::DI_NORMAL
+            );
+            
+            let result = save_bitmap_as_png(hbmp, icon_path);
+            
+            let _ = windows::Win32::Graphics::Gdi::SelectObject(hdc_mem, old_obj);
+            let _ = windows::Win32::Graphics::Gdi::DeleteObject(hbmp.into());
+            let _ = windows::Win32::Graphics::Gdi::DeleteDC(hdc_mem);
+            let _ = windows::Win32::Graphics::Gdi::ReleaseDC(None, hdc);
+            let _ = windows::Win32::UI::WindowsAndMessaging::DestroyIcon(hicon);
+            
+            return result;
+        }
+    }
+    
+    false
+}
+
+fn save_bitmap_as_png(hbmp: windows::Win32::Graphics::Gdi::HBITMAP, path: &Path) -> bool {
+    unsafe {
+        let mut bmi: windows::Win32::Graphics::Gdi::BITMAPINFO = std::mem::zeroed();
+        bmi.bmiHeader.biSize = std::mem::size_of::<windows::Win32::Graphics::Gdi::BITMAPINFOHEADER>() as u32;
+        bmi.bmiHeader.biWidth = 48;
+        bmi.bmiHeader.biHeight = -48;
+        bmi.bmiHeader.biPlanes = 1;
+        bmi.bmiHeader.biBitCount = 32;
+        bmi.bmiHeader.biCompression = windows::Win32::Graphics::Gdi::BI_RGB.0;
+        
+        let data_size = 48 * 48 * 4;
+        let mut buffer = vec![0u8; data_size];
+        
+        let dc = windows::Win32::Graphics::Gdi::GetDC(None);
+        let result = windows::Win32::Graphics::Gdi::GetDIBits(
+            dc,
+            hbmp,
+            0,
+            48,
+            Some(buffer.as_mut_ptr() as *mut std::ffi::c_void),
+            &mut bmi,
+            windows::Win32::Graphics::Gdi::DIB_RGB_COLORS
+        );
+        let _ = windows::Win32::Graphics::Gdi::ReleaseDC(None, dc);
+        
+        if result == 0 {
+            return false;
+        }
+        
+        let file = match File::create(path) {
+            Ok(f) => f,
+            Err(_) => return false,
+        };
+        let writer = std::io::BufWriter::new(file);
+        
+        use image::{ImageBuffer, Rgba};
+        
+        let mut img = ImageBuffer::<Rgba<u8>, _>::new(48, 48);
+        
+        for y in 0..48 {
+            for x in 0..48 {
+                let i = (y * 48 + x) * 4;
+                let b = buffer[i];
+                let g = buffer[i + 1];
+                let r = buffer[i + 2];
+                let a = buffer[i + 3];
+                
+                img.put_pixel(x as u32, y as u32, Rgba([r, g, b, a]));
+            }
+        }
+        
+        img.write_to(&mut writer.into_inner().unwrap(), ImageOutputFormat::Png).is_ok()
+    }
+}
+
+pub(crate) fn collect_apps() -> Vec<AppInfo> {
+    use rayon::prelude::*;
+    
+    let mut all_apps = Vec::new();
+    
+    let shortcuts = get_shortcuts_from_special_folders();
+    all_apps.extend(shortcuts);
+    
+    let registry_apps = get_installed_apps_from_registry();
+    all_apps.extend(registry_apps);
+    
+    all_apps.par_iter_mut().for_each(|app| {
+        if app.icon_path.is_none() {
+            (app.icon_path, app.icon_path_2x) = app_icons(app);
+        }
+    });
+    
+    all_apps
+}
+
+fn is_exe_file(path: &Path) -> bool {
+    path.extension().is_some_and(|ext| ext.eq_ignore_ascii_case("exe"))
+}
+
+// Takes the shortcuts and executables out of `files`, which come from the
+// extra scan roots.
+pub(crate) fn root_apps(files: &mut Vec<PathBuf>) -> Vec<AppInfo> {
+    use rayon::prelude::*;
+    
+    let (paths, rest): (Vec<_>, Vec<_>) = files.drain(..)
+        .partition(|path| is_shortcut_file(path) || (is_exe_file(path) && path.is_file()));
+    *files = rest;
+    
+    let mut apps: Vec<AppInfo> = paths.iter()
+        .filter_map(|path| {
+            if is_shortcut_file(path) {
+                return shortcut_to_app(path);
+            }
+            
+            Some(AppInfo {
+                name: path.file_stem()?.to_string_lossy().to_string(),
+                path: path.to_string_lossy().to_string(),
+                source: Some(path.to_string_lossy().to_string()),
+                ..Default::default()
+            })
+        })
+        .collect();
+    
+    apps.par_iter_mut().for_each(|app| {
+        (app.icon_path, app.icon_path_2x) = app_icons(app);
+    });
+    
+    apps
+}
+
+pub(crate) fn watch_roots() -> Vec<PathBuf> {
+    get_special_folders()
+}
+
+pub(crate) fn rescan_file(path: &Path) -> Option<(Vec<String>, Vec<AppInfo>)> {
+    if !is_shortcut_file(path) {
+        return None;
+    }
+    
+    let mut apps = Vec::new();
+    if let Some(mut app) = shortcut_to_app(path) {
+        (app.icon_path, app.icon_path_2x) = app_icons(&app);
+        apps.push(app);
+    }
+    
+    Some((vec![path.to_string_lossy().to_string()], apps))
+}
+
+// Shortcut arguments are a raw command line; pass them through untouched so
+// the target sees exactly what Explorer would give it.
+pub(crate) fn launch_app(app: &AppInfo) -> Result<(), String> {
+    use std::os::windows::process::CommandExt;
+    
+    let mut command = Command::new(&app.path);
+    if let Some(arguments) = &app.arguments {
+        command.raw_arg(arguments);
+    }
+    if let Some(dir) = app.working_dir.as_ref().filter(|dir| Path::new(dir).is_dir()) {
+        command.current_dir(dir);
+    }
+    
+    command.spawn()
+        .map(|_| ())
+        .map_err(|e| format!("Open App Failed: {}", e))
+}
@@ -0,0 +1,413 @@
+use std::env;
+use std::fs;
+use std::cell::OnceCell;
+use std::collections::HashSet;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+use crate::{config, exe_select, icon_cache, registry, scan, AppInfo};
+use crate::desktop_exec::tokenize_exec;
+use crate::registry::TextRegistry;
+use crate::shell_link::{expand_vars, ShellLink};
+
+const ICON_SIZE: u32 = 48;
+
+// Windows-side folders that hold shortcuts, relative to the prefix's
+// environment. Older Wine versions keep the start menu under the profile.
+const SHORTCUT_DIRS: &[&str] = &[
+    "%APPDATA%\\Microsoft\\Windows\\Start Menu\\Programs",
+    "%USERPROFILE%\\Start Menu\\Programs",
+    "%ProgramData%\\Microsoft\\Windows\\Start Menu\\Programs",
+    "%USERPROFILE%\\Desktop",
+    "%PUBLIC%\\Desktop",
+];
+
+// Configured prefixes, or $WINEPREFIX, ~/.wine and winetricks' prefixes.
+// Only directories that look like initialized prefixes are returned.
+pub(crate) fn prefixes() -> Vec<PathBuf> {
+    let configured = &config::get().wine_prefixes;
+
+    let candidates: Vec<PathBuf> = if !configured.is_empty() {
+        configured.iter().map(|p| config::expand_home(p)).collect()
+    } else {
+        let mut candidates = Vec::new();
+        if let Some(prefix) = env::var_os("WINEPREFIX").filter(|v| !v.is_empty()) {
+            candidates.push(PathBuf::from(prefix));
+        }
+        if let Some(home) = dirs::home_dir() {
+            candidates.push(home.join(".wine"));
+        }
+        if let Some(data_dir) = dirs::data_dir() {
+            if let Ok(entries) = fs::read_dir(data_dir.join("wineprefixes")) {
+                let mut winetricks: Vec<PathBuf> = entries
+                    .filter_map(|e| e.ok())
+                    .map(|e| e.path())
+                    .collect();
+                winetricks.sort();
+                candidates.extend(winetricks);
+            }
+        }
+        candidates
+    };
+
+    let mut seen = HashSet::new();
+    candidates.into_iter()
+        .filter(|prefix| prefix.join("system.reg").is_file() && prefix.join("drive_c").is_dir())
+        .filter(|prefix| seen.insert(fs::canonicalize(prefix).unwrap_or_else(|_| prefix.clone())))
+        .collect()
+}
+
+struct WinePrefix {
+    root: PathBuf,
+    user: String,
+}
+
+impl WinePrefix {
+    fn new(root: PathBuf) -> Self {
+        let users_dir = root.join("drive_c").join("users");
+
+        let user = env::var("USER")
+            .ok()
+            .filter(|user| users_dir.join(user).is_dir())
+            .or_else(|| {
+                fs::read_dir(&users_dir).ok()?
+                    .filter_map(|e| e.ok())
+                    .map(|e| e.file_name().to_string_lossy().to_string())
+                    .find(|name| name != "Public")
+            })
+            .unwrap_or_else(|| "Public".to_string());
+
+        Self { root, user }
+    }
+
+    // The variables Wine sets up for every process in a default prefix.
+    fn env_var(&self, name: &str) -> Option<String> {
+        let profile = format!("C:\\users\\{}", self.user);
+
+        let value = match name.to_ascii_lowercase().as_str() {
+            "systemdrive" => "C:".to_string(),
+            "systemroot" | "windir" => "C:\\windows".to_string(),
+            "programfiles" | "programw6432" => "C:\\Program Files".to_string(),
+            "programfiles(x86)" => "C:\\Program Files (x86)".to_string(),
+            "commonprogramfiles" => "C:\\Program Files\\Common Files".to_string(),
+            "programdata" | "allusersprofile" => "C:\\ProgramData".to_string(),
+            "public" => "C:\\users\\Public".to_string(),
+            "username" => self.user.clone(),
+            "userprofile" => profile,
+            "appdata" => format!("{}\\AppData\\Roaming", profile),
+            "localappdata" => format!("{}\\AppData\\Local", profile),
+            _ => return None,
+        };
+
+        Some(value)
+    }
+
+    // Maps a Windows path inside the prefix to the host file system, the way
+    // Wine does: through drive_c or the dosdevices drive links, matching each
+    // component case-insensitively.
+    fn to_host(&self, windows_path: &str) -> Option<PathBuf> {
+        let path = expand_vars(windows_path.trim().trim_matches('"'), |name| self.env_var(name));
+
+        let mut chars = path.chars();
+        let drive = chars.next().filter(|c| c.is_ascii_alphabetic())?.to_ascii_lowercase();
+        if chars.next() != Some(':') {
+            return None;
+        }
+
+        let drive_link = self.root.join("dosdevices").join(format!("{}:", drive));
+        let mut host = if drive == 'c' && self.root.join("drive_c").is_dir() {
+            self.root.join("drive_c")
+        } else if drive_link.exists() {
+            drive_link
+        } else {
+            return None;
+        };
+
+        for part in path[2..].split(['\\', '/']).filter(|p| !p.is_empty()) {
+            host = match_component(&host, part);
+        }
+
+        Some(host)
+    }
+
+    fn shortcut_dirs(&self) -> Vec<PathBuf> {
+        SHORTCUT_DIRS.iter()
+            .filter_map(|dir| self.to_host(dir))
+            .filter(|dir| dir.is_dir())
+            .collect()
+    }
+}
+
+fn match_component(dir: &Path, name: &str) -> PathBuf {
+    let exact = dir.join(name);
+    if exact.exists() {
+        return exact;
+    }
+
+    fs::read_dir(dir).ok()
+        .and_then(|entries| {
+            entries.filter_map(|e| e.ok())
+                .find(|e| e.file_name().to_string_lossy().eq_ignore_ascii_case(name))
+                .map(|e| e.path())
+        })
+        .unwrap_or(exact)
+}
+
+fn registry_apps(prefix: &WinePrefix) -> Vec<AppInfo> {
+    let mut apps = Vec::new();
+    let prefix_root = prefix.root.to_string_lossy().to_string();
+
+    for (reg_file, root) in [("system.reg", "HKEY_LOCAL_MACHINE"), ("user.reg", "HKEY_CURRENT_USER")] {
+        let reg_path = prefix.root.join(reg_file);
+        let mut registry = TextRegistry::default();
+        if let Err(e) = registry.load_file(&reg_path, root) {
+            eprintln!("Failed to read registry {}: {}", reg_path.display(), e);
+            continue;
+        }
+        let source = reg_path.to_string_lossy().to_string();
+
+        let app = |name: String, path: PathBuf| AppInfo {
+            name,
+            path: path.to_string_lossy().to_string(),
+            is_shortcut: false,
+            wine_prefix: Some(prefix_root.clone()),
+            source: Some(source.clone()),
+            ..Default::default()
+        };
+
+        for entry in registry::uninstall_entries(&registry) {
+            let candidates = match prefix.to_host(&entry.install_location) {
+                Some(dir) => exe_select::candidates(&dir),
+                None => continue,
+            };
+            let display_icon = entry.display_icon.as_deref().and_then(|icon| prefix.to_host(icon));
+
+            if let Some(selection) = exe_select::select(&candidates, &entry.name, display_icon.as_deref()) {
+                apps.push(AppInfo {
+                    selection_rationale: Some(selection.rationale),
+                    ..app(entry.name, selection.path)
+                });
+            }
+        }
+
+        for entry in registry::app_path_entries(&registry) {
+            if let Some(path) = prefix.to_host(&entry.path).filter(|path| path.is_file()) {
+                apps.push(app(entry.name, path));
+            }
+        }
+    }
+
+    apps
+}
+
+fn shortcut_to_app(prefix: &WinePrefix, path: &Path) -> Option<AppInfo> {
+    let link = match ShellLink::read(path) {
+        Ok(link) => link,
+        Err(e) => {
+            eprintln!("Failed to read shortcut {}: {}", path.display(), e);
+            return None;
+        }
+    };
+
+    let target = match link.target() {
+        Some(target) => prefix.to_host(target)?,
+        None => path.parent()?.join(link.relative_path.as_ref()?),
+    };
+
+    let name = path.file_stem()?.to_string_lossy().to_string();
+
+    Some(AppInfo {
+        name,
+        path: target.to_string_lossy().to_string(),
+        is_shortcut: true,
+        arguments: link.arguments.clone().filter(|args| !args.is_empty()),
+        working_dir: link.working_dir.as_deref()
+            .and_then(|dir| prefix.to_host(dir))
+            .map(|dir| dir.to_string_lossy().to_string()),
+        icon_name: link.icon()
+            .and_then(|icon| prefix.to_host(icon))
+            .map(|icon| icon.to_string_lossy().to_string()),
+        icon_index: link.icon_index,
+        wine_prefix: Some(prefix.root.to_string_lossy().to_string()),
+        source: Some(path.to_string_lossy().to_string()),
+        ..Default::default()
+    })
+}
+
+fn is_shortcut_file(path: &Path) -> bool {
+    path.extension().is_some_and(|ext| ext.eq_ignore_ascii_case("lnk"))
+}
+
+fn shortcut_apps(prefix: &WinePrefix) -> Vec<AppInfo> {
+    prefix.shortcut_dirs()
+        .iter()
+        .flat_map(|dir| {
+            scan::walk(dir, None)
+                .filter(|e| e.file_type().is_file() && is_shortcut_file(e.path()))
+                .filter_map(|e| shortcut_to_app(prefix, e.path()))
+                .collect::<Vec<_>>()
+        })
+        .collect()
+}
+
+fn resource_icon(source: &Path, index: i32, size: u32) -> Option<String> {
+    icon_cache::global().get_or_insert_with(source, &format!("icon-{}-{}", size, index), |out| {
+        icon_cache::render_icon_resource(source, index, size, out)
+    })
+}
+
+fn set_icons(app: &mut AppInfo) {
+    let (source, index) = match &app.icon_name {
+        Some(location) => (PathBuf::from(location), app.icon_index),
+        None => (PathBuf::from(&app.path), 0),
+    };
+
+    app.icon_path = resource_icon(&source, index, ICON_SIZE);
+    app.icon_path_2x = resource_icon(&source, index, ICON_SIZE * 2);
+}
+
+pub(crate) fn collect_apps() -> Vec<AppInfo> {
+    use rayon::prelude::*;
+
+    let mut apps: Vec<AppInfo> = prefixes()
+        .into_iter()
+        .map(WinePrefix::new)
+        .flat_map(|prefix| {
+            let mut apps = shortcut_apps(&prefix);
+            apps.extend(registry_apps(&prefix));
+            apps
+        })
+        .collect();
+
+    apps.par_iter_mut().for_each(set_icons);
+
+    apps
+}
+
+// Registry files live next to drive_c, and watching the whole prefix would
+// mean watching every installed file; they are picked up on full rescans.
+pub(crate) fn watch_roots() -> Vec<PathBuf> {
+    prefixes()
+        .into_iter()
+        .flat_map(|prefix| WinePrefix::new(prefix).shortcut_dirs())
+        .collect()
+}
+
+// `prefixes` is shared by the files of a watcher batch and only looked up
+// once one of them is a shortcut.
+pub(crate) fn rescan_file(path: &Path, prefixes: &OnceCell<Vec<PathBuf>>) -> Option<(Vec<String>, Vec<AppInfo>)> {
+    if !is_shortcut_file(path) {
+        return None;
+    }
+
+    let prefix = prefixes.get_or_init(self::prefixes)
+        .iter()
+        .find(|prefix| path.starts_with(prefix))
+        .cloned()
+        .map(WinePrefix::new)?;
+
+    let apps = shortcut_to_app(&prefix, path)
+        .map(|mut app| {
+            set_icons(&mut app);
+            app
+        })
+        .into_iter()
+        .collect();
+
+    Some((vec![path.to_string_lossy().to_string()], apps))
+}
+
+// winemenubuilder writes `.desktop` files for every shortcut, with an Exec
+// line like `env WINEPREFIX="/home/u/.wine" wine C:\\...\\App.lnk`. Those are
+// dropped when the prefix is indexed here, since the shortcut itself carries
+// more (arguments, icon, working directory).
+pub(crate) fn is_indexed_wine_launcher(exec: &str, prefixes: &[PathBuf]) -> bool {
+    let tokens = match tokenize_exec(exec) {
+        Ok(tokens) => tokens,
+        Err(_) => return false,
+    };
+
+    let runs_wine = tokens.iter().any(|token| {
+        let program = Path::new(token).file_name().map(|n| n.to_string_lossy().to_string()).unwrap_or_default();
+        program == "wine" || program.starts_with("wine64") || program.starts_with("wine-")
+    });
+    if !runs_wine {
+        return false;
+    }
+
+    let prefix = tokens.iter()
+        .find_map(|token| token.strip_prefix("WINEPREFIX="))
+        .map(PathBuf::from)
+        .or_else(|| dirs::home_dir().map(|home| home.join(".wine")));
+
+    prefix.is_some_and(|prefix| {
+        let prefix = fs::canonicalize(&prefix).unwrap_or(prefix);
+        prefixes.iter().any(|p| fs::canonicalize(p).unwrap_or_else(|_| p.clone()) == prefix)
+    })
+}
+
+// Splits a Windows command line the way CommandLineToArgvW does: quotes
+// group, and backslashes only escape when they precede a quote.
+fn split_command_line(line: &str) -> Vec<String> {
+    let mut args = Vec::new();
+    let mut current = String::new();
+    let mut in_quotes = false;
+    let mut has_arg = false;
+    let mut backslashes = 0;
+
+    for c in line.chars() {
+        match c {
+            '\\' => backslashes += 1,
+            '"' => {
+                current.extend(std::iter::repeat_n('\\', backslashes / 2));
+                if backslashes % 2 == 1 {
+                    current.push('"');
+                } else {
+                    in_quotes = !in_quotes;
+                    has_arg = true;
+                }
+                backslashes = 0;
+            }
+            c if c.is_whitespace() && !in_quotes => {
+                current.extend(std::iter::repeat_n('\\', backslashes));
+                backslashes = 0;
+                if has_arg || !current.is_empty() {
+                    args.push(std::mem::take(&mut current));
+                    has_arg = false;
+                }
+            }
+            c => {
+                current.extend(std::iter::repeat_n('\\', backslashes));
+                backslashes = 0;
+                current.push(c);
+            }
+        }
+    }
+    current.extend(std::iter::repeat_n('\\', backslashes));
+    if has_arg || !current.is_empty() {
+        args.push(current);
+    }
+
+    args
+}
+
+pub(crate) fn launch_app(app: &AppInfo) -> Result<(), String> {
+    let prefix = app.wine_prefix.as_ref().ok_or("Not a Wine application")?;
+
+    // `start /unix` takes a host path and also opens documents through the
+    // prefix's file associations.
+    let mut command = Command::new("wine");
+    command.env("WINEPREFIX", prefix)
+        .args(["start", "/unix", &app.path]);
+
+    if let Some(arguments) = &app.arguments {
+        command.args(split_command_line(arguments));
+    }
+    if let Some(dir) = app.working_dir.as_ref().filter(|dir| Path::new(dir).is_dir()) {
+        command.current_dir(dir);
+    }
+
+    command.spawn()
+        .map(|_| ())
+        .map_err(|e| format!("Open App Failed: {}", e))
+}